@@ -0,0 +1,526 @@
+//! Kitty genetics: decoding DNA into named visual traits and inheriting DNA when breeding.
+//!
+//! This is the one canonical decoding of `Kitty.dna`. It is used by the runtime, exposed through
+//! the `KittiesApi`, and can be linked by off-chain renderers through the `std` helpers below.
+//!
+//! Gene `g` occupies two DNA bytes: byte `2 * g` holds the dominant allele, which is the one
+//! expressed, and byte `2 * g + 1` holds the recessive allele the kitty carries and may pass on.
+//! Lower allele values dominate higher ones, so high values are rare looks.
+
+#[cfg(feature = "std")]
+use serde::{Serialize, Deserialize};
+use parity_codec::{Encode, Decode};
+use rstd::cmp;
+
+/// Index of each gene.
+pub const BODY_COLOR_GENE: usize = 0;
+pub const EYE_SHAPE_GENE: usize = 1;
+pub const PATTERN_GENE: usize = 2;
+pub const ACCESSORY_GENE: usize = 3;
+pub const RARITY_GENE: usize = 4;
+
+/// Number of genes in the DNA.
+pub const GENE_COUNT: usize = 5;
+
+/// Position of the DNA byte whose lowest bit decides the gender, right after the genes.
+pub const GENDER_BYTE: usize = 2 * GENE_COUNT;
+
+/// Mutation rates are given in parts per million.
+pub const MUTATION_RATE_SCALE: u32 = 1_000_000;
+
+/// Seed bytes used per gene: one for picking the parents' alleles and four for the mutation roll.
+const SEED_BYTES_PER_GENE: usize = 5;
+
+/// The color of the kitty's fur.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+pub enum BodyColor {
+	Ginger,
+	Black,
+	White,
+	Grey,
+	Cream,
+	Cinnamon,
+	Lilac,
+	Calico,
+}
+
+/// The shape of the kitty's eyes.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+pub enum EyeShape {
+	Round,
+	Almond,
+	Sleepy,
+	Wide,
+	Sly,
+	Crazy,
+}
+
+/// The pattern of the kitty's fur.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+pub enum Pattern {
+	Solid,
+	Tabby,
+	Spotted,
+	Tuxedo,
+	Ticked,
+	Tortoiseshell,
+}
+
+/// What the kitty is wearing.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+pub enum Accessory {
+	Nothing,
+	Collar,
+	Bowtie,
+	Hat,
+	Glasses,
+	Crown,
+}
+
+/// How rare the kitty's overall look is.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+pub enum Rarity {
+	Common,
+	Uncommon,
+	Rare,
+	Epic,
+	Legendary,
+}
+
+/// The gender of a kitty. Only a male and a female can breed.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+pub enum Gender {
+	Male,
+	Female,
+}
+
+impl Default for Gender {
+	fn default() -> Self {
+		Gender::Male
+	}
+}
+
+/// The visual traits encoded in a kitty's DNA.
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+pub struct KittyTraits {
+	pub body_color: BodyColor,
+	pub eye_shape: EyeShape,
+	pub pattern: Pattern,
+	pub accessory: Accessory,
+	pub rarity: Rarity,
+}
+
+impl BodyColor {
+	fn from_gene(gene: u8) -> Self {
+		match gene % 8 {
+			0 => BodyColor::Ginger,
+			1 => BodyColor::Black,
+			2 => BodyColor::White,
+			3 => BodyColor::Grey,
+			4 => BodyColor::Cream,
+			5 => BodyColor::Cinnamon,
+			6 => BodyColor::Lilac,
+			_ => BodyColor::Calico,
+		}
+	}
+
+	/// Human readable name of the trait.
+	pub fn name(&self) -> &'static str {
+		match self {
+			BodyColor::Ginger => "Ginger",
+			BodyColor::Black => "Black",
+			BodyColor::White => "White",
+			BodyColor::Grey => "Grey",
+			BodyColor::Cream => "Cream",
+			BodyColor::Cinnamon => "Cinnamon",
+			BodyColor::Lilac => "Lilac",
+			BodyColor::Calico => "Calico",
+		}
+	}
+}
+
+impl EyeShape {
+	fn from_gene(gene: u8) -> Self {
+		match gene % 6 {
+			0 => EyeShape::Round,
+			1 => EyeShape::Almond,
+			2 => EyeShape::Sleepy,
+			3 => EyeShape::Wide,
+			4 => EyeShape::Sly,
+			_ => EyeShape::Crazy,
+		}
+	}
+
+	/// Human readable name of the trait.
+	pub fn name(&self) -> &'static str {
+		match self {
+			EyeShape::Round => "Round",
+			EyeShape::Almond => "Almond",
+			EyeShape::Sleepy => "Sleepy",
+			EyeShape::Wide => "Wide",
+			EyeShape::Sly => "Sly",
+			EyeShape::Crazy => "Crazy",
+		}
+	}
+}
+
+impl Pattern {
+	fn from_gene(gene: u8) -> Self {
+		match gene % 6 {
+			0 => Pattern::Solid,
+			1 => Pattern::Tabby,
+			2 => Pattern::Spotted,
+			3 => Pattern::Tuxedo,
+			4 => Pattern::Ticked,
+			_ => Pattern::Tortoiseshell,
+		}
+	}
+
+	/// Human readable name of the trait.
+	pub fn name(&self) -> &'static str {
+		match self {
+			Pattern::Solid => "Solid",
+			Pattern::Tabby => "Tabby",
+			Pattern::Spotted => "Spotted",
+			Pattern::Tuxedo => "Tuxedo",
+			Pattern::Ticked => "Ticked",
+			Pattern::Tortoiseshell => "Tortoiseshell",
+		}
+	}
+}
+
+impl Accessory {
+	fn from_gene(gene: u8) -> Self {
+		match gene % 6 {
+			0 => Accessory::Nothing,
+			1 => Accessory::Collar,
+			2 => Accessory::Bowtie,
+			3 => Accessory::Hat,
+			4 => Accessory::Glasses,
+			_ => Accessory::Crown,
+		}
+	}
+
+	/// Human readable name of the trait.
+	pub fn name(&self) -> &'static str {
+		match self {
+			Accessory::Nothing => "Nothing",
+			Accessory::Collar => "Collar",
+			Accessory::Bowtie => "Bowtie",
+			Accessory::Hat => "Hat",
+			Accessory::Glasses => "Glasses",
+			Accessory::Crown => "Crown",
+		}
+	}
+}
+
+impl Rarity {
+	/// Higher gene values are increasingly rare: 160 of the 256 values are common and only one is
+	/// legendary.
+	fn from_gene(gene: u8) -> Self {
+		match gene {
+			0..=159 => Rarity::Common,
+			160..=223 => Rarity::Uncommon,
+			224..=247 => Rarity::Rare,
+			248..=254 => Rarity::Epic,
+			_ => Rarity::Legendary,
+		}
+	}
+
+	/// Human readable name of the trait.
+	pub fn name(&self) -> &'static str {
+		match self {
+			Rarity::Common => "Common",
+			Rarity::Uncommon => "Uncommon",
+			Rarity::Rare => "Rare",
+			Rarity::Epic => "Epic",
+			Rarity::Legendary => "Legendary",
+		}
+	}
+}
+
+fn allele(dna: &[u8], gene: usize, recessive: bool) -> u8 {
+	dna.get(2 * gene + recessive as usize).cloned().unwrap_or(0)
+}
+
+/// Whether the kitty carries a recessive allele that it doesn't express.
+fn carries_recessive(dna: &[u8], gene: usize) -> bool {
+	allele(dna, gene, true) != allele(dna, gene, false)
+}
+
+/// The gender encoded in the DNA. Missing bytes decode as male.
+pub fn gender(dna: &[u8]) -> Gender {
+	match dna.get(GENDER_BYTE).cloned().unwrap_or(0) & 1 {
+		0 => Gender::Male,
+		_ => Gender::Female,
+	}
+}
+
+/// Decode DNA bytes into named traits from the expressed alleles. Genes beyond the end of `dna`
+/// decode as zero.
+pub fn decode(dna: &[u8]) -> KittyTraits {
+	let gene = |gene: usize| allele(dna, gene, false);
+
+	KittyTraits {
+		body_color: BodyColor::from_gene(gene(BODY_COLOR_GENE)),
+		eye_shape: EyeShape::from_gene(gene(EYE_SHAPE_GENE)),
+		pattern: Pattern::from_gene(gene(PATTERN_GENE)),
+		accessory: Accessory::from_gene(gene(ACCESSORY_GENE)),
+		rarity: Rarity::from_gene(gene(RARITY_GENE)),
+	}
+}
+
+/// Write the DNA of a child of `dna_1` and `dna_2` into `child`, which must be as long as the
+/// parents' DNA. The result only depends on the inputs, so a fixed `seed` gives a fixed child.
+///
+/// For every gene the child gets one allele from each parent, picked by the seed, and expresses
+/// the dominant one. If both parents carry a recessive allele they don't express, the gene mutates
+/// with the given rate (in parts per million, indexed by gene): the child then gets two copies of
+/// an allele one step rarer than both parents' recessive alleles, so the mutation is expressed.
+/// The bytes after the genes are crossed over from the parents at random.
+pub fn inherit(dna_1: &[u8], dna_2: &[u8], seed: &[u8], mutation_rates: &[u32], child: &mut [u8]) {
+	let seed_byte = |i: usize| seed.get(i).cloned().unwrap_or(0) as u32;
+	let genes = cmp::min(GENE_COUNT, child.len() / 2);
+
+	for gene in 0..genes {
+		let offset = gene * SEED_BYTES_PER_GENE;
+		let picks = seed_byte(offset);
+		let roll = seed_byte(offset + 1)
+			| seed_byte(offset + 2) << 8
+			| seed_byte(offset + 3) << 16
+			| seed_byte(offset + 4) << 24;
+		let rate = mutation_rates.get(gene).cloned().unwrap_or(0);
+
+		let (dominant, recessive) = if carries_recessive(dna_1, gene)
+			&& carries_recessive(dna_2, gene)
+			&& roll % MUTATION_RATE_SCALE < rate
+		{
+			let mutation = cmp::max(allele(dna_1, gene, true), allele(dna_2, gene, true)).saturating_add(1);
+			(mutation, mutation)
+		} else {
+			let from_1 = allele(dna_1, gene, picks & 1 == 1);
+			let from_2 = allele(dna_2, gene, picks & 2 == 2);
+			(cmp::min(from_1, from_2), cmp::max(from_1, from_2))
+		};
+
+		child[2 * gene] = dominant;
+		child[2 * gene + 1] = recessive;
+	}
+
+	let crossover_offset = GENE_COUNT * SEED_BYTES_PER_GENE;
+	for i in 2 * genes..child.len() {
+		let bit = i - 2 * genes;
+		let from_2 = (seed_byte(crossover_offset + bit / 8) >> (bit % 8)) & 1 == 1;
+		let parent = if from_2 { dna_2 } else { dna_1 };
+		child[i] = parent.get(i).cloned().unwrap_or(0);
+	}
+}
+
+/// Turn random bytes into valid gen-0 DNA by ordering the two alleles of every gene so the lower,
+/// dominant one comes first. Bytes after the genes are left as they are.
+pub fn order_alleles(dna: &mut [u8]) {
+	let genes = cmp::min(GENE_COUNT, dna.len() / 2);
+
+	for gene in 0..genes {
+		if dna[2 * gene] > dna[2 * gene + 1] {
+			dna.swap(2 * gene, 2 * gene + 1);
+		}
+	}
+}
+
+/// Render the traits as a markdown table, e.g. for CLI tools and off-chain renderers.
+#[cfg(feature = "std")]
+pub fn render_table(traits: &KittyTraits) -> String {
+	let rows = [
+		("Body color", traits.body_color.name()),
+		("Eye shape", traits.eye_shape.name()),
+		("Pattern", traits.pattern.name()),
+		("Accessory", traits.accessory.name()),
+		("Rarity", traits.rarity.name()),
+	];
+
+	let mut table = String::from("| Gene       | Value         |\n|------------|---------------|\n");
+	for (gene, value) in rows.iter() {
+		table.push_str(&format!("| {:<10} | {:<13} |\n", gene, value));
+	}
+	table
+}
+
+/// tests for this module
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// Parents whose genes are all `dominant` over `recessive`.
+	fn parent(dominant: u8, recessive: u8) -> [u8; 32] {
+		let mut dna = [7u8; 32];
+		for gene in 0..GENE_COUNT {
+			dna[2 * gene] = dominant;
+			dna[2 * gene + 1] = recessive;
+		}
+		dna
+	}
+
+	// A fixed sequence of seeds so the distributions below are reproducible.
+	fn seeds(count: usize) -> Vec<[u8; 32]> {
+		let mut state = 0x2545_f491_4f6c_dd1du64;
+		(0..count).map(|_| {
+			let mut seed = [0u8; 32];
+			for byte in seed.iter_mut() {
+				state ^= state << 13;
+				state ^= state >> 7;
+				state ^= state << 17;
+				*byte = (state >> 32) as u8;
+			}
+			seed
+		}).collect()
+	}
+
+	fn breed(dna_1: &[u8; 32], dna_2: &[u8; 32], seed: &[u8; 32], mutation_rates: &[u32]) -> [u8; 32] {
+		let mut child = [0u8; 32];
+		inherit(dna_1, dna_2, seed, mutation_rates, &mut child);
+		child
+	}
+
+	#[test]
+	fn decode_is_deterministic() {
+		let dna = [9u8, 0, 4, 0, 13, 0, 5, 0, 255, 0, 1, 2, 3];
+
+		assert_eq!(decode(&dna), KittyTraits {
+			body_color: BodyColor::Black,
+			eye_shape: EyeShape::Sly,
+			pattern: Pattern::Tabby,
+			accessory: Accessory::Crown,
+			rarity: Rarity::Legendary,
+		});
+		assert_eq!(decode(&dna), decode(&dna));
+	}
+
+	#[test]
+	fn short_dna_decodes_as_zero_genes() {
+		assert_eq!(decode(&[]), decode(&[0u8; 32]));
+		assert_eq!(gender(&[]), Gender::Male);
+	}
+
+	#[test]
+	fn gender_follows_the_gender_byte() {
+		let mut dna = [0u8; 32];
+		dna[GENDER_BYTE] = 2;
+		assert_eq!(gender(&dna), Gender::Male);
+		dna[GENDER_BYTE] = 3;
+		assert_eq!(gender(&dna), Gender::Female);
+	}
+
+	#[test]
+	fn rarity_thresholds() {
+		assert_eq!(Rarity::from_gene(159), Rarity::Common);
+		assert_eq!(Rarity::from_gene(160), Rarity::Uncommon);
+		assert_eq!(Rarity::from_gene(224), Rarity::Rare);
+		assert_eq!(Rarity::from_gene(248), Rarity::Epic);
+		assert_eq!(Rarity::from_gene(255), Rarity::Legendary);
+	}
+
+	#[test]
+	fn inherit_is_deterministic() {
+		let dna_1 = parent(10, 200);
+		let dna_2 = parent(20, 100);
+
+		for seed in seeds(16).iter() {
+			assert_eq!(breed(&dna_1, &dna_2, seed, &[]), breed(&dna_1, &dna_2, seed, &[]));
+		}
+	}
+
+	#[test]
+	fn inherit_takes_one_allele_from_each_parent() {
+		let dna_1 = parent(10, 200);
+		let dna_2 = parent(20, 100);
+		// Gene 0 takes the dominant allele of parent 1 and the recessive one of parent 2, gene 1
+		// the other way round.
+		let mut seed = [0u8; 32];
+		seed[0] = 0b10;
+		seed[SEED_BYTES_PER_GENE] = 0b01;
+
+		let child = breed(&dna_1, &dna_2, &seed, &[]);
+
+		assert_eq!(&child[0..2], &[10, 100]);
+		assert_eq!(&child[2..4], &[20, 200]);
+		// Every other gene takes both dominant alleles.
+		assert_eq!(&child[4..6], &[10, 20]);
+		// Bytes after the genes come from parent 1 when the seed bits are zero.
+		assert_eq!(&child[2 * GENE_COUNT..], &dna_1[2 * GENE_COUNT..]);
+	}
+
+	#[test]
+	fn recessive_trait_shows_in_a_quarter_of_offspring() {
+		let carrier = parent(10, 200);
+
+		let expressed = seeds(4_000).iter()
+			.filter(|seed| breed(&carrier, &carrier, seed, &[])[0] == 200)
+			.count();
+
+		assert!(expressed > 900 && expressed < 1_100, "expressed in {} of 4000", expressed);
+	}
+
+	#[test]
+	fn mutation_needs_recessive_carriers_on_both_sides() {
+		let carrier = parent(10, 200);
+		let pure = parent(10, 10);
+		let always = [MUTATION_RATE_SCALE; GENE_COUNT];
+
+		for seed in seeds(64).iter() {
+			let child = breed(&carrier, &pure, seed, &always);
+			assert!(child[0] == 10 || child[0] == 200);
+
+			let child = breed(&carrier, &carrier, seed, &always);
+			assert_eq!(&child[0..2], &[201, 201]);
+		}
+	}
+
+	#[test]
+	fn mutation_follows_the_configured_rate() {
+		let carrier = parent(10, 200);
+		let rates = [MUTATION_RATE_SCALE / 10, 0, 0, 0, 0];
+
+		let children: Vec<_> = seeds(4_000).iter().map(|seed| breed(&carrier, &carrier, seed, &rates)).collect();
+		let mutated = children.iter().filter(|child| child[0] == 201).count();
+
+		assert!(mutated > 300 && mutated < 500, "mutated {} of 4000", mutated);
+		assert!(children.iter().all(|child| child[2] != 201));
+	}
+
+	#[test]
+	fn order_alleles_puts_the_dominant_allele_first() {
+		let mut dna = [7u8; 32];
+		dna[0] = 200;
+		dna[1] = 10;
+		dna[2] = 10;
+		dna[3] = 200;
+		dna[GENDER_BYTE] = 255;
+		dna[GENDER_BYTE + 1] = 0;
+
+		order_alleles(&mut dna);
+
+		assert_eq!(&dna[0..4], &[10, 200, 10, 200]);
+		assert_eq!(&dna[GENDER_BYTE..GENDER_BYTE + 2], &[255, 0]);
+		for seed in seeds(64).iter_mut() {
+			order_alleles(seed);
+			assert!((0..GENE_COUNT).all(|gene| allele(seed, gene, false) <= allele(seed, gene, true)));
+		}
+	}
+
+	#[test]
+	fn render_table_lists_every_gene() {
+		let table = render_table(&decode(&[0u8; 32]));
+
+		assert!(table.contains("| Body color | Ginger        |"));
+		assert!(table.contains("| Rarity     | Common        |"));
+		assert_eq!(table.lines().count(), 7);
+	}
+}
@@ -54,12 +54,65 @@ pub type BlockNumber = u64;
 /// Index of an account's extrinsic in the chain.
 pub type Nonce = u64;
 
+/// Balance of an account.
+pub type Balance = u128;
+
+/// A kitty as returned by the `KittiesApi`.
+pub type Kitty = substratekitties::Kitty<Hash, Balance, BlockNumber>;
+
+/// The metadata of a kitty as returned by the `KittiesApi`.
+pub type KittyMetadata = substratekitties::KittyMetadata<Hash>;
+
 // for ./substratekitties.rs 
 mod substratekitties;
 
+/// Canonical decoding of kitty DNA into visual traits in `./genetics.rs`
+pub mod genetics;
+
 /// Used for the module template in `./template.rs`
 mod template;
 
+/// Runtime API for querying kitties. This gives clients a stable, versioned way to read kitties
+/// without computing storage keys for the `substratekitties` module.
+pub mod kitties_api {
+	use super::{AccountId, Balance, Hash, Kitty, KittyMetadata, genetics::KittyTraits};
+	use rstd::prelude::*;
+	use client::decl_runtime_apis;
+
+	decl_runtime_apis! {
+		/// The API to query kitties.
+		pub trait KittiesApi {
+			/// Returns the kitty with the given id, if it exists.
+			fn kitty(kitty_id: Hash) -> Option<Kitty>;
+			/// Returns up to `limit` kitties owned by `owner`, starting at the `start`th one.
+			fn kitties_of_owner(owner: AccountId, start: u64, limit: u64) -> Vec<Kitty>;
+			/// Returns up to `limit` kitties that are for sale, starting at the `start`th one.
+			/// Kitties come in rising price ranges, in no particular order within a range.
+			fn kitties_for_sale(start: u64, limit: u64) -> Vec<Kitty>;
+			/// Returns up to `limit` kitties listed at `max_price` or less, starting at the `start`th one.
+			fn kitties_for_sale_below(max_price: Balance, start: u64, limit: u64) -> Vec<Kitty>;
+			/// Returns the number of kitties for sale.
+			fn for_sale_count() -> u64;
+			/// Returns the price a buyer would pay for the kitty in the current block.
+			fn current_price(kitty_id: Hash) -> Balance;
+			/// Returns the total number of kitties in existence.
+			fn total_supply() -> u64;
+			/// Returns the parents of a bred kitty.
+			fn parents(kitty_id: Hash) -> Option<(Hash, Hash)>;
+			/// Returns all children of a kitty.
+			fn children(kitty_id: Hash) -> Vec<Hash>;
+			/// Returns the visual traits decoded from the kitty's DNA, if it exists.
+			fn kitty_traits(kitty_id: Hash) -> Option<KittyTraits>;
+			/// Returns the name of a kitty, if it has one.
+			fn kitty_name(kitty_id: Hash) -> Option<Vec<u8>>;
+			/// Returns the kitty with the given name, if there is one.
+			fn kitty_by_name(name: Vec<u8>) -> Option<Kitty>;
+			/// Returns the metadata of a kitty, if it has any.
+			fn kitty_metadata(kitty_id: Hash) -> Option<KittyMetadata>;
+		}
+	}
+}
+
 /// Opaque types. These are used by the CLI to instantiate machinery that don't need to know
 /// the specifics of the runtime. They can then be made to be agnostic over specific formats
 /// of data like extrinsics, allowing for them to continue syncing the network through upgrades
@@ -97,8 +150,8 @@ pub const VERSION: RuntimeVersion = RuntimeVersion {
 	spec_name: create_runtime_str!("substratekitties"),
 	impl_name: create_runtime_str!("substratekitties"),
 	authoring_version: 3,
-	spec_version: 4,
-	impl_version: 4,
+	spec_version: 5,
+	impl_version: 5,
 	apis: RUNTIME_API_VERSIONS,
 };
 
@@ -170,7 +223,7 @@ impl timestamp::Trait for Runtime {
 
 impl balances::Trait for Runtime {
 	/// The type for recording an account's balance.
-	type Balance = u128;
+	type Balance = Balance;
 	/// What to do if an account's free balance gets zeroed.
 	type OnFreeBalanceZero = ();
 	/// What to do if a new account is created.
@@ -192,6 +245,29 @@ impl sudo::Trait for Runtime {
 // impl for substratekitties module
 impl substratekitties::Trait for Runtime {
 	type Event = Event;
+	/// Aura doesn't tell us the author's account, so fees can't be sent to the block author.
+	type BlockAuthor = ();
+	/// Breeding cooldowns per generation, from one minute up to a week at 10 second blocks.
+	const BREEDING_COOLDOWNS: &'static [u64] = &[
+		6, 12, 30, 60, 180, 360, 720, 1_440, 2_880, 5_760, 8_640, 17_280, 34_560, 60_480,
+	];
+	/// Mutation rates per gene in parts per million; rarity mutates least often.
+	const MUTATION_RATES: &'static [u32] = &[20_000, 20_000, 20_000, 10_000, 5_000];
+	/// Creation and breeding secrets are revealed at least a minute after the commitment and within an hour.
+	const REVEAL_DELAY: u64 = 6;
+	const REVEAL_PERIOD: u64 = 360;
+	/// Names are 3 to 32 bytes and metadata URIs up to 256 bytes, each byte held with a deposit.
+	const MIN_NAME_LENGTH: usize = 3;
+	const MAX_NAME_LENGTH: usize = 32;
+	const MAX_METADATA_URI_LENGTH: usize = 256;
+	const DEPOSIT_PER_BYTE: u64 = 1_000;
+	/// Large enough for an airdrop of a few hundred kitties in one extrinsic.
+	const MAX_BATCH_SIZE: usize = 500;
+	/// At most this many listings are ended per block; the rest carry over to the next block.
+	const MAX_LISTING_EXPIRIES_PER_BLOCK: usize = 100;
+	/// Offers hold real funds and each kitty takes a bounded number, so spam can't grow its offer list.
+	const MAX_OFFERS_PER_KITTY: usize = 20;
+	const MIN_OFFER_AMOUNT: u64 = 10_000;
 }
 
 /// Used for the module template in `./template.rs`
@@ -312,4 +388,58 @@ impl_runtime_apis! {
 			Consensus::authorities()
 		}
 	}
+
+	impl kitties_api::KittiesApi<Block> for Runtime {
+		fn kitty(kitty_id: Hash) -> Option<Kitty> {
+			Substratekitties::kitty_details(kitty_id)
+		}
+
+		fn kitties_of_owner(owner: AccountId, start: u64, limit: u64) -> Vec<Kitty> {
+			Substratekitties::kitties_of_owner(&owner, start, limit)
+		}
+
+		fn kitties_for_sale(start: u64, limit: u64) -> Vec<Kitty> {
+			Substratekitties::kitties_for_sale(start, limit)
+		}
+
+		fn kitties_for_sale_below(max_price: Balance, start: u64, limit: u64) -> Vec<Kitty> {
+			Substratekitties::kitties_for_sale_below(max_price, start, limit)
+		}
+
+		fn for_sale_count() -> u64 {
+			Substratekitties::for_sale_count()
+		}
+
+		fn current_price(kitty_id: Hash) -> Balance {
+			Substratekitties::current_price(kitty_id)
+		}
+
+		fn total_supply() -> u64 {
+			Substratekitties::total_supply()
+		}
+
+		fn parents(kitty_id: Hash) -> Option<(Hash, Hash)> {
+			Substratekitties::parents_of(kitty_id)
+		}
+
+		fn children(kitty_id: Hash) -> Vec<Hash> {
+			Substratekitties::children_of(kitty_id)
+		}
+
+		fn kitty_traits(kitty_id: Hash) -> Option<genetics::KittyTraits> {
+			Substratekitties::traits_of(kitty_id)
+		}
+
+		fn kitty_name(kitty_id: Hash) -> Option<Vec<u8>> {
+			Substratekitties::name_of(kitty_id)
+		}
+
+		fn kitty_by_name(name: Vec<u8>) -> Option<Kitty> {
+			Substratekitties::kitty_by_name(name).and_then(Substratekitties::kitty_details)
+		}
+
+		fn kitty_metadata(kitty_id: Hash) -> Option<KittyMetadata> {
+			Substratekitties::metadata_of(kitty_id)
+		}
+	}
 }
\ No newline at end of file
@@ -1,23 +1,175 @@
-use support::{decl_storage, decl_module, StorageValue, StorageMap,
-    dispatch::Result, ensure, decl_event, traits::Currency};
-use system::ensure_signed;
+use support::{decl_storage, decl_module, StorageValue, StorageMap, StorageDoubleMap,
+    dispatch::Result, ensure, decl_event,
+    traits::{Currency, ReservableCurrency, WithdrawReason, ExistenceRequirement}};
+use system::{ensure_signed, ensure_root};
+use runtime_primitives::Permill;
 use runtime_primitives::traits::{As, Hash, Zero};
 use parity_codec::{Encode, Decode};
 use rstd::cmp;
+use rstd::prelude::*;
+use crate::genetics::{self, Gender, KittyTraits};
 
 // Kitty用のランタイムカスタム構造体を作成
 #[derive(Encode, Decode, Default, Clone, PartialEq)]
 #[cfg_attr(feature = "std", derive(Debug))]
-pub struct Kitty<Hash, Balance> {
+// ランタイムAPIを通してクライアントがデコードするためフィールドは公開する
+pub struct Kitty<Hash, Balance, BlockNumber> {
+    pub id: Hash,
+    pub dna: Hash,
+    pub price: Balance,
+    pub gen: u64,
+    // 交配できるようになるブロック(最初のレイアウトから移行したKittyは0)
+    pub ready_at: BlockNumber,
+    // 種親として貸し出している場合の交配料(最初のレイアウトから移行したKittyはNone)
+    pub sire_fee: Option<Balance>,
+    // mint時にDNAから決まる性別
+    pub gender: Gender,
+}
+
+// 最初にデプロイされたKittyのレイアウト(ストレージの移行にだけ使う)
+#[derive(Encode, Decode)]
+struct KittyV0<Hash, Balance> {
     id: Hash,
     dna: Hash,
     price: Balance,
     gen: u64,
 }
 
+// 現在のストレージのバージョン
+// 0: Kittyはid, dna, price, genだけを持つ
+// 1: Kittyにクールダウン、種親の交配料、性別がある
+// 2: 売りに出されているKittyの価格帯ごとのインデックスがある
+const STORAGE_VERSION: u32 = 2;
+
+// 売りに出されているKittyを分ける価格帯の数(価格のlog2で分けるため、u128の価格まで扱える)
+const PRICE_BUCKETS: u32 = 128;
+
+// イングリッシュオークション用のランタイムカスタム構造体
+#[derive(Encode, Decode, Clone, PartialEq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct Auction<AccountId, Balance, BlockNumber> {
+    seller: AccountId,
+    reserve: Balance,
+    end_block: BlockNumber,
+    highest_bid: Balance,
+    highest_bidder: Option<AccountId>,
+}
+
+// ダッチオークション(価格逓減型の販売)用のランタイムカスタム構造体
+#[derive(Encode, Decode, Clone, PartialEq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct DutchAuction<Balance, BlockNumber> {
+    start_price: Balance,
+    end_price: Balance,
+    start_block: BlockNumber,
+    duration: BlockNumber,
+}
+
+// 売りに出されていないKittyへの購入オファー
+#[derive(Encode, Decode, Clone, PartialEq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct Offer<Balance, BlockNumber> {
+    amount: Balance,
+    expires_at: BlockNumber,
+}
+
+// Kittyに紐づけるオフチェーンのメタデータ
+#[derive(Encode, Decode, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct KittyMetadata<Hash> {
+    pub uri: Vec<u8>,
+    // uriの指す内容のハッシュ
+    pub content_hash: Hash,
+}
+
+// revealを待っている交配のコミットメント
+#[derive(Encode, Decode, Clone, PartialEq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct BreedCommitment<Hash, Balance, BlockNumber> {
+    kitty_id_1: Hash,
+    kitty_id_2: Hash,
+    // 種親を借りる場合に確保している交配料
+    sire_fee: Option<Balance>,
+    reveal_at: BlockNumber,
+    expires_at: BlockNumber,
+}
+
+// revealを待っている第0世代のKittyの作成のコミットメント
+#[derive(Encode, Decode, Clone, PartialEq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct CreateCommitment<Balance, BlockNumber> {
+    // revealまで確保している作成手数料
+    fee: Balance,
+    reveal_at: BlockNumber,
+    expires_at: BlockNumber,
+}
+
+// 作成・交配の手数料の行き先
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub enum FeeDestination<AccountId> {
+    // 総発行量から取り除く
+    Burn,
+    // トレジャリーなど指定したアカウントに送る
+    Account(AccountId),
+    // ブロック作成者に送る(作成者が分からないランタイムでは設定できない)
+    BlockAuthor,
+}
+
+impl<AccountId> Default for FeeDestination<AccountId> {
+    fn default() -> Self {
+        FeeDestination::Burn
+    }
+}
+
+// 現在のブロックの作成者を知る方法
+pub trait FindBlockAuthor<AccountId> {
+    fn block_author() -> Option<AccountId>;
+}
+
+// 作成者が分からないランタイム向け
+impl<AccountId> FindBlockAuthor<AccountId> for () {
+    fn block_author() -> Option<AccountId> {
+        None
+    }
+}
+
 pub trait Trait: balances::Trait {
     // 外部用のEventタイプを定義
     type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
+
+    // FeeDestination::BlockAuthorの手数料を受け取るアカウントを探す
+    type BlockAuthor: FindBlockAuthor<Self::AccountId>;
+
+    // 交配後のクールダウン(ブロック数)を世代ごとに並べたテーブル
+    // 世代がテーブルの長さを超えた場合は最後の値を使う
+    const BREEDING_COOLDOWNS: &'static [u64];
+
+    // 遺伝子ごとの突然変異率(100万分率)。genetics::GENE_COUNTより短い場合、残りの遺伝子は変異しない
+    const MUTATION_RATES: &'static [u32];
+
+    // コミットしてから、ハッシュをDNAに使うブロックまでのブロック数(revealはその次のブロックからできる)
+    const REVEAL_DELAY: u64;
+    // ハッシュをDNAに使うブロックからコミットメントが失効するまでのブロック数
+    const REVEAL_PERIOD: u64;
+
+    // 名前の長さの範囲(バイト数)
+    const MIN_NAME_LENGTH: usize;
+    const MAX_NAME_LENGTH: usize;
+    // メタデータのURIの最大長(バイト数)
+    const MAX_METADATA_URI_LENGTH: usize;
+    // 名前とメタデータの1バイトごとに確保するデポジット
+    const DEPOSIT_PER_BYTE: u64;
+
+    // transfer_batchとmint_batchで一度に扱えるKittyの最大数
+    const MAX_BATCH_SIZE: usize;
+
+    // 1ブロックで終了させる期限切れの販売の最大数(残りは次のブロックに回す)
+    const MAX_LISTING_EXPIRIES_PER_BLOCK: usize;
+
+    // 1匹のKittyに同時に出せるオファーの最大数と、オファーの最低額
+    const MAX_OFFERS_PER_KITTY: usize;
+    const MIN_OFFER_AMOUNT: u64;
 }
 
 // 外部発信用のイベント
@@ -26,20 +178,59 @@ decl_event!(
     where
         <T as system::Trait>::AccountId,
         <T as system::Trait>::Hash,
+        <T as system::Trait>::BlockNumber,
         <T as balances::Trait>::Balance
     {
         // 各種イベント
         Created(AccountId, Hash),
+        CreateCommitted(AccountId, Hash, BlockNumber),
+        CreateCommitmentExpired(AccountId, Hash),
         PriceSet(AccountId, Hash, Balance),
         Transferred(AccountId, AccountId, Hash),
         Bought(AccountId, AccountId, Hash, Balance),
+        Burned(AccountId, Hash),
+        AuctionCreated(AccountId, Hash, Balance, BlockNumber),
+        BidPlaced(AccountId, Hash, Balance),
+        AuctionSettled(AccountId, AccountId, Hash, Balance),
+        AuctionCancelled(AccountId, Hash),
+        DutchAuctionCreated(AccountId, Hash, Balance, Balance, BlockNumber),
+        OfferMade(AccountId, Hash, Balance, BlockNumber),
+        OfferAccepted(AccountId, AccountId, Hash, Balance),
+        OfferWithdrawn(AccountId, Hash),
+        OfferExpired(AccountId, Hash),
+        OfferOutbid(AccountId, Hash),
+        RoyaltyPaid(AccountId, Hash, Balance),
+        RoyaltyRateSet(Permill),
+        SiringApproved(AccountId, AccountId, Hash),
+        SireListed(AccountId, Hash, Balance),
+        SireUsed(AccountId, AccountId, Hash, Balance),
+        SireDelisted(AccountId, Hash),
+        Bred(AccountId, Hash, Hash, Hash),
+        BreedCommitted(AccountId, Hash, BlockNumber),
+        BreedCommitmentExpired(AccountId, Hash),
+        OffspringToFemaleOwnerSet(bool),
+        NameSet(AccountId, Hash),
+        NameCleared(AccountId, Hash),
+        MetadataSet(AccountId, Hash),
+        MetadataCleared(AccountId, Hash),
+        Approval(AccountId, AccountId, Hash),
+        ApprovalForAll(AccountId, AccountId, bool),
+        MaxSupplySet(Option<u64>),
+        MaxOwnedPerAccountSet(Option<u64>),
+        MintFeeSet(Balance),
+        BreedFeeSet(Balance),
+        FeeDestinationSet(FeeDestination<AccountId>),
+        FeePaid(AccountId, Balance),
+        Listed(AccountId, Hash, Balance, BlockNumber),
+        Delisted(AccountId, Hash),
+        ListingExpired(AccountId, Hash),
     }
 );
 
 decl_storage! {
     trait Store for Module<T: Trait> as KittyStorage {
         // idをKittiyオブジェクトにマッピングする新しいKittiesストレージ
-        Kitties get(kitty): map T::Hash => Kitty<T::Hash, T::Balance>;
+        Kitties get(kitty): map T::Hash => Kitty<T::Hash, T::Balance, T::BlockNumber>;
         // kittyを所有するアカウントIDにkittyidをマッピングするKittyOwnerストレージ
         KittyOwner get(owner_of): map T::Hash => Option<T::AccountId>;
 
@@ -58,6 +249,85 @@ decl_storage! {
 
         // 一意の数字Nonce
         Nonce: u64;
+
+        // Kittyを最初にmintしたアカウント(ロイヤリティの受取人)
+        KittyCreator get(creator_of): map T::Hash => Option<T::AccountId>;
+        // 二次流通の際に作成者へ支払うロイヤリティの割合(rootが変更できる)
+        RoyaltyRate get(royalty_rate): Permill;
+
+        // 交配で生まれたKittyの両親
+        KittyParents get(parents_of): map T::Hash => Option<(T::Hash, T::Hash)>;
+        // Kittyごとの子供のリスト(家系図の表示や近親交配のチェックに使う)
+        KittyChildrenArray get(child_of_kitty_by_index): map (T::Hash, u64) => T::Hash;
+        KittyChildrenCount get(children_count): map T::Hash => u64;
+        // 所有者以外に一度だけ交配を許可する相手
+        SireAllowedTo get(sire_allowed_to): map T::Hash => Option<T::AccountId>;
+        // 種親として貸し出されて交配に使われた回数
+        SireUses get(sire_uses): map T::Hash => u64;
+
+        // 開催中のオークション(存在する間はKittyがロックされる)
+        Auctions get(auction_of): map T::Hash => Option<Auction<T::AccountId, T::Balance, T::BlockNumber>>;
+        // 終了ブロックごとに精算するオークションのリスト
+        AuctionsEndingAt get(auctions_ending_at): map T::BlockNumber => Vec<T::Hash>;
+
+        // ダッチオークションの価格設定(Kitty.priceには開始価格が入り、売りに出されているKittyのインデックスには終了価格で入る)
+        DutchAuctions get(dutch_auction_of): map T::Hash => Option<DutchAuction<T::Balance, T::BlockNumber>>;
+
+        // Kittyとオファーを出したアカウントの組で管理する購入オファー
+        Offers get(offer_of): double_map T::Hash, blake2_256(T::AccountId) => Option<Offer<T::Balance, T::BlockNumber>>;
+        // Kittyごとにオファーを出しているアカウントのリスト(MAX_OFFERS_PER_KITTY以下)
+        OfferBidders get(offer_bidders): map T::Hash => Vec<T::AccountId>;
+        // 期限ブロックごとに失効させるオファーのリスト
+        OffersExpiringAt: map T::BlockNumber => Vec<(T::Hash, T::AccountId)>;
+
+        // コミットしたアカウントとsecretのハッシュをキーにしたrevealを待っている作成と交配
+        // アカウントごとに分けるため、他人が同じハッシュを先にコミットしても邪魔できない
+        CreateCommitments get(create_commitment): double_map T::AccountId, blake2_256(T::Hash) => Option<CreateCommitment<T::Balance, T::BlockNumber>>;
+        BreedCommitments get(breed_commitment): double_map T::AccountId, blake2_256(T::Hash) => Option<BreedCommitment<T::Hash, T::Balance, T::BlockNumber>>;
+        // 期限ブロックごとに失効させるコミットメントのリスト
+        CreateCommitmentsExpiringAt: map T::BlockNumber => Vec<(T::AccountId, T::Hash)>;
+        BreedCommitmentsExpiringAt: map T::BlockNumber => Vec<(T::AccountId, T::Hash)>;
+
+        // trueの場合、交配で生まれたKittyはメスの所有者のものになる(rootが変更できる)
+        OffspringToFemaleOwner get(offspring_to_female_owner): bool;
+
+        // Kittyの名前と、名前からKittyを引くための一意なインデックス
+        KittyNames get(name_of): map T::Hash => Option<Vec<u8>>;
+        KittyByName get(kitty_by_name): map Vec<u8> => Option<T::Hash>;
+        // Kittyのメタデータ
+        KittyMetadataOf get(metadata_of): map T::Hash => Option<KittyMetadata<T::Hash>>;
+        // 名前とメタデータのために確保されているデポジットと、それを確保したアカウント
+        KittyDeposits get(deposit_of): map T::Hash => Option<(T::AccountId, T::Balance)>;
+
+        // 所有者の代わりにKittyを移転できるアカウント(移転すると消える)
+        KittyApprovals get(approved_for): map T::Hash => Option<T::AccountId>;
+        // 所有者の全てのKittyを代わりに移転・承認できるオペレーター
+        OperatorApprovals get(is_approved_for_all): double_map T::AccountId, blake2_256(T::AccountId) => bool;
+
+        // Kittyの総数と、1アカウントが所有できる数の上限(rootが変更できる、Noneなら上限なし)
+        MaxSupply get(max_supply): Option<u64>;
+        MaxOwnedPerAccount get(max_owned_per_account): Option<u64>;
+
+        // reveal_createとreveal_breedで呼び出したアカウントから徴収する手数料(rootが変更できる)
+        MintFee get(mint_fee): T::Balance;
+        BreedFee get(breed_fee): T::Balance;
+        // 徴収した手数料の行き先(rootが変更できる)
+        FeeDestinationOf get(fee_destination): FeeDestination<T::AccountId>;
+
+        // 価格帯ごとの売りに出されているKittyのリスト(価格帯は販売価格のlog2、ダッチオークションは開始価格)
+        ForSaleArray get(for_sale_by_bucket_index): map (u32, u64) => T::Hash;
+        ForSaleBucketCount get(for_sale_bucket_count): map u32 => u64;
+        // 売りに出されているKittyの価格帯とリスト内の位置
+        ForSalePosition get(for_sale_position): map T::Hash => Option<(u32, u64)>;
+        // 売りに出されているKittyの総数
+        ForSaleCount get(for_sale_count): u64;
+        // list_for_saleで設定した販売の期限
+        ListingExpiry get(listing_expires_at): map T::Hash => Option<T::BlockNumber>;
+        // 期限ブロックごとに終了させる販売のリスト
+        ListingsExpiringAt: map T::BlockNumber => Vec<T::Hash>;
+
+        // ストレージの移行に使うバージョン
+        StorageVersion get(storage_version): u32;
     }
 }
 
@@ -67,30 +337,174 @@ decl_module! {
         // Eventをデポジットするための関数　＝＞　ランタイム開発の一般的なパターン
         fn deposit_event<T>() = default;
 
-        // Kittyを作成するための関数
-        fn create_kitty(origin) -> Result {
+        // 古いレイアウトのストレージが残っていればブロックの最初に移行する
+        fn on_initialize(n: T::BlockNumber) {
+            let version = Self::storage_version();
+            if version < 1 {
+                Self::migrate_kitties();
+            }
+            if version < 2 {
+                Self::index_kitties_for_sale();
+            }
+            if version < STORAGE_VERSION {
+                <StorageVersion<T>>::put(STORAGE_VERSION);
+            }
+
+            Self::expire_listings(n);
+        }
+
+        // 終了ブロックを迎えたオークションをブロックの最後に精算する
+        fn on_finalize(n: T::BlockNumber) {
+            for kitty_id in <AuctionsEndingAt<T>>::take(n) {
+                Self::settle_auction(kitty_id);
+            }
+
+            // 期限を迎えたオファーを失効させて資金を返却する
+            for (kitty_id, bidder) in <OffersExpiringAt<T>>::take(n) {
+                if let Some(offer) = Self::offer_of(&kitty_id, &bidder) {
+                    if offer.expires_at == n {
+                        Self::remove_offer(kitty_id, &bidder);
+                        <balances::Module<T> as ReservableCurrency<_>>::unreserve(&bidder, offer.amount);
+                        Self::deposit_event(RawEvent::OfferExpired(bidder, kitty_id));
+                    }
+                }
+            }
+
+            // revealされなかったコミットメントを失効させて手数料を返却する
+            for (owner, commitment) in <CreateCommitmentsExpiringAt<T>>::take(n) {
+                if let Some(pending) = Self::create_commitment(&owner, &commitment) {
+                    if pending.expires_at == n {
+                        <CreateCommitments<T>>::remove(&owner, &commitment);
+                        <balances::Module<T> as ReservableCurrency<_>>::unreserve(&owner, pending.fee);
+                        Self::deposit_event(RawEvent::CreateCommitmentExpired(owner, commitment));
+                    }
+                }
+            }
+            for (owner, commitment) in <BreedCommitmentsExpiringAt<T>>::take(n) {
+                if let Some(pending) = Self::breed_commitment(&owner, &commitment) {
+                    if pending.expires_at == n {
+                        <BreedCommitments<T>>::remove(&owner, &commitment);
+                        if let Some(fee) = pending.sire_fee {
+                            <balances::Module<T> as ReservableCurrency<_>>::unreserve(&owner, fee);
+                        }
+                        Self::deposit_event(RawEvent::BreedCommitmentExpired(owner, commitment));
+                    }
+                }
+            }
+        }
+
+        // Kittyの作成を予約する関数
+        // commitmentはsecretのハッシュで、REVEAL_DELAYブロック後にreveal_createでsecretを公開する
+        // 作成手数料はrevealまで確保され、revealされずに失効すると返却される
+        fn commit_create(origin, commitment: T::Hash) -> Result {
             // originを確認
             let sender = ensure_signed(origin)?;
-            
-            // random_seedを使ってランダムハッシュを作成
-            let nonce = <Nonce<T>>::get();
-            let random_hash = (<system::Module<T>>::random_seed(), &sender, nonce)
+
+            ensure!(Self::create_commitment(&sender, &commitment).is_none(), "This commitment is already in use");
+
+            // 手数料を確保する前にmintできるか確認する
+            Self::ensure_can_mint(1)?;
+            Self::ensure_can_receive(&sender)?;
+
+            let fee = Self::mint_fee();
+            let (reveal_at, expires_at) = Self::reveal_window();
+
+            <balances::Module<T> as ReservableCurrency<_>>::reserve(&sender, fee)?;
+
+            <CreateCommitments<T>>::insert(&sender, &commitment, &CreateCommitment { fee, reveal_at, expires_at });
+            <CreateCommitmentsExpiringAt<T>>::mutate(expires_at, |commitments| commitments.push((sender.clone(), commitment)));
+
+            Self::deposit_event(RawEvent::CreateCommitted(sender, commitment, reveal_at));
+
+            Ok(())
+        }
+
+        // secretを公開して予約した第0世代のKittyを作成する関数
+        // DNAはsecretと、コミットした時点では決まっていなかったreveal_atのブロックハッシュから作られる
+        fn reveal_create(origin, secret: T::Hash) -> Result {
+            let sender = ensure_signed(origin)?;
+
+            let commitment = <T as system::Trait>::Hashing::hash_of(&secret);
+            let pending = Self::create_commitment(&sender, &commitment).ok_or("There is no creation commitment for this secret")?;
+            ensure!(<system::Module<T>>::block_number() > pending.reveal_at, "This creation commitment can't be revealed yet");
+
+            Self::ensure_can_mint(1)?;
+            Self::ensure_can_receive(&sender)?;
+
+            // 確保していた作成手数料を支払う
+            <balances::Module<T> as ReservableCurrency<_>>::unreserve(&sender, pending.fee);
+            Self::charge_fee(&sender, pending.fee)?;
+
+            <CreateCommitments<T>>::remove(&sender, &commitment);
+
+            let random_hash = (<system::Module<T>>::block_hash(pending.reveal_at), secret, &sender)
                 .using_encoded(<T as system::Trait>::Hashing::hash);
 
-            // Kittyオブジェクトを使ってnew_kittyを作成する
-            // new_kittyの中身にRuntimeストレージのデータを初期化↓データを入れる
-            let new_kitty = Kitty {
-                id: random_hash,
-                dna: random_hash,
-                price: <T::Balance as As<u64>>::sa(0),
-                gen: 0,
-            };
+            Self::create_gen0_kitty(sender, random_hash)
+        }
+
+        // ownerにcount匹のKittyをまとめて作成する関数(root専用、全て作成できる場合のみ実行される)
+        fn mint_batch(origin, owner: T::AccountId, count: u64) -> Result {
+            ensure_root(origin)?;
+
+            ensure!(count > 0, "The batch is empty");
+            ensure!(count <= T::MAX_BATCH_SIZE as u64, "The batch is too large");
+
+            // 途中で失敗しないよう、作成する前に全体の数を確認する
+            Self::ensure_can_mint(count)?;
+            Self::ensure_can_receive_many(&owner, count)?;
+
+            // rootが配布するKittyなのでrandom_seedから作る
+            for _ in 0..count {
+                let nonce = <Nonce<T>>::get();
+                let random_hash = (<system::Module<T>>::random_seed(), &owner, nonce)
+                    .using_encoded(<T as system::Trait>::Hashing::hash);
+
+                Self::create_gen0_kitty(owner.clone(), random_hash)?;
 
-            // リファクタリングしたMintを使う
-            Self::mint(sender, random_hash, new_kitty)?;
+                <Nonce<T>>::put(nonce + 1);
+            }
+
+            Ok(())
+        }
+
+        // 期限付きでKittyを売りに出す関数
+        // 期限のブロックを迎えると販売は終了する
+        fn list_for_sale(origin, kitty_id: T::Hash, price: T::Balance, expires_at: T::BlockNumber) -> Result {
+            let sender = ensure_signed(origin)?;
+
+            let owner = Self::owner_of(kitty_id).ok_or("No owner for this kitty")?;
+            ensure!(owner == sender, "You do not own this kitty");
+
+            Self::ensure_not_in_auction(kitty_id)?;
+
+            ensure!(!price.is_zero(), "The price must be greater than zero");
+            ensure!(expires_at > <system::Module<T>>::block_number(), "The listing must expire in the future");
+
+            // ダッチオークションは取り消される
+            Self::set_listing_price(kitty_id, price);
+            <DutchAuctions<T>>::remove(kitty_id);
+            <ListingExpiry<T>>::insert(kitty_id, expires_at);
+            <ListingsExpiringAt<T>>::mutate(expires_at, |kitty_ids| kitty_ids.push(kitty_id));
+
+            Self::deposit_event(RawEvent::Listed(sender, kitty_id, price, expires_at));
+
+            Ok(())
+        }
 
-            // Nonceを一つ増やす
-            <Nonce<T>>::mutate(|n| *n += 1);
+        // 販売を取りやめる関数
+        fn delist(origin, kitty_id: T::Hash) -> Result {
+            let sender = ensure_signed(origin)?;
+
+            let owner = Self::owner_of(kitty_id).ok_or("No owner for this kitty")?;
+            ensure!(owner == sender, "You do not own this kitty");
+
+            ensure!(!Self::kitty(kitty_id).price.is_zero(), "This kitty is not for sale");
+
+            Self::set_listing_price(kitty_id, Zero::zero());
+            <DutchAuctions<T>>::remove(kitty_id);
+
+            Self::deposit_event(RawEvent::Delisted(sender, kitty_id));
 
             Ok(())
         }
@@ -106,17 +520,105 @@ decl_module! {
             let owner = Self::owner_of(kitty_id).ok_or("No owner for this kitty")?;
             ensure!(owner == sender, "You do not own this cat");
 
-            let mut kitty = Self::kitty(kitty_id);
+            Self::ensure_not_in_auction(kitty_id)?;
 
             // 新しい価格の代入し、ストレージの情報をアップデート
-            kitty.price = new_price;
-            <Kitties<T>>::insert(kitty_id, kitty);
+            // 固定価格を設定するとダッチオークションは取り消される
+            Self::set_listing_price(kitty_id, new_price);
+            <DutchAuctions<T>>::remove(kitty_id);
 
             Self::deposit_event(RawEvent::PriceSet(sender, kitty_id, new_price));
             
             Ok(())
         }
 
+        // 自分のKittyを移転できるアカウントを承認する関数(所有者かオペレーターが呼べる)
+        // 自分自身を承認すると承認は取り消される
+        fn approve(origin, spender: T::AccountId, kitty_id: T::Hash) -> Result {
+            let sender = ensure_signed(origin)?;
+
+            let owner = Self::owner_of(kitty_id).ok_or("No owner for this kitty")?;
+            ensure!(owner == sender || Self::is_approved_for_all(&owner, &sender),
+                "You are neither the owner nor an operator of this kitty");
+
+            if spender == owner {
+                <KittyApprovals<T>>::remove(kitty_id);
+            } else {
+                <KittyApprovals<T>>::insert(kitty_id, &spender);
+            }
+
+            Self::deposit_event(RawEvent::Approval(owner, spender, kitty_id));
+
+            Ok(())
+        }
+
+        // 自分の全てのKittyを扱えるオペレーターを承認、または取り消す関数
+        fn set_approval_for_all(origin, operator: T::AccountId, approved: bool) -> Result {
+            let sender = ensure_signed(origin)?;
+
+            ensure!(operator != sender, "You can't approve yourself as an operator");
+
+            if approved {
+                <OperatorApprovals<T>>::insert(&sender, &operator, &true);
+            } else {
+                <OperatorApprovals<T>>::remove(&sender, &operator);
+            }
+
+            Self::deposit_event(RawEvent::ApprovalForAll(sender, operator, approved));
+
+            Ok(())
+        }
+
+        // 承認されたアカウントかオペレーターが、所有者の代わりにKittyを移転する関数
+        fn transfer_from_approved(origin, from: T::AccountId, to: T::AccountId, kitty_id: T::Hash) -> Result {
+            let sender = ensure_signed(origin)?;
+
+            let owner = Self::owner_of(kitty_id).ok_or("No owner for this kitty")?;
+            ensure!(owner == from, "from account does not own this kitty");
+            ensure!(
+                sender == owner
+                    || Self::approved_for(kitty_id).as_ref() == Some(&sender)
+                    || Self::is_approved_for_all(&owner, &sender),
+                "You are not approved to transfer this kitty"
+            );
+
+            Self::ensure_not_in_auction(kitty_id)?;
+
+            Self::transfer_from(from, to, kitty_id)?;
+
+            Ok(())
+        }
+
+        // 複数のKittyをまとめて移転する関数(全て移転できる場合のみ実行される)
+        fn transfer_batch(origin, to: T::AccountId, kitty_ids: Vec<T::Hash>) -> Result {
+            let sender = ensure_signed(origin)?;
+
+            ensure!(!kitty_ids.is_empty(), "The batch is empty");
+            ensure!(kitty_ids.len() <= T::MAX_BATCH_SIZE, "The batch is too large");
+
+            // 途中で失敗しないよう、移転する前に全てのKittyを確認する
+            for (i, kitty_id) in kitty_ids.iter().enumerate() {
+                ensure!(!kitty_ids[..i].contains(kitty_id), "The batch contains the same kitty twice");
+
+                let owner = Self::owner_of(kitty_id).ok_or("No owner for this kitty")?;
+                ensure!(owner == sender, "You do not own this kitty");
+
+                Self::ensure_not_in_auction(*kitty_id)?;
+            }
+            if to != sender {
+                Self::ensure_can_receive_many(&to, kitty_ids.len() as u64)?;
+            }
+
+            for kitty_id in kitty_ids {
+                Self::transfer_from(sender.clone(), to.clone(), kitty_id)
+                    .expect("every kitty is shown to be owned by `sender`; \
+                    `to` is shown to be able to receive all of them; \
+                    qed");
+            }
+
+            Ok(())
+        }
+
         // Kittyの所有権を移転する関数
         fn transfer(origin, to: T::AccountId, kitty_id: T::Hash) -> Result {
             let sender = ensure_signed(origin)?;
@@ -124,6 +626,8 @@ decl_module! {
             let owner = Self::owner_of(kitty_id).ok_or("No owner for this kitty")?;
             ensure!(owner == sender, "You do not own this kitty");
 
+            Self::ensure_not_in_auction(kitty_id)?;
+
             Self::transfer_from(sender, to, kitty_id)?;
 
             Ok(())
@@ -140,157 +644,3473 @@ decl_module! {
             let owner = Self::owner_of(kitty_id).ok_or("No owner for this kitty")?;
             ensure!(owner != sender, "You can't buy your own cat");
 
-            let mut kitty = Self::kitty(kitty_id);
+            Self::ensure_not_in_auction(kitty_id)?;
 
             // Zero Traitsを使ってkittyの値段が0でない(＝売りに出されている)か調べる＋言い値以下かチェック
-            let kitty_price = kitty.price;
-            ensure!(kitty_price.is_zero(), "This cat you want to buy is not for sale");
+            // ダッチオークション中であれば現在のブロックでの価格を使う
+            let kitty_price = Self::current_price(kitty_id);
+            ensure!(!kitty_price.is_zero(), "This cat you want to buy is not for sale");
             ensure!(kitty_price <= max_price, "The cat you want to buy is costs more than your max price");
 
-            // Balanceモジュールのtransfer()を使って資金を移転する
-            <balances::Module<T> as Currency<_>>::transfer(&sender, &owner, kitty_price)?;
-            
+            // 途中で送金が失敗しないよう、先に残高を確認しておく
+            ensure!(<balances::Module<T> as Currency<_>>::free_balance(&sender) >= kitty_price,
+                "You don't have enough free balance to buy this cat");
+            Self::ensure_can_receive(&sender)?;
+
+            // Balanceモジュールのtransfer()を使って売り手と作成者に代金を支払う
+            Self::pay_sale(&sender, &owner, kitty_id, kitty_price, false)?;
+
+            // kittyを市場から戻す
+            Self::set_listing_price(kitty_id, Zero::zero());
+            <DutchAuctions<T>>::remove(kitty_id);
+
             // ACTION: Transfer the kitty using `transfer_from()` including a proof of why it cannot fail
             Self::transfer_from(owner.clone(), sender.clone(), kitty_id)
             .expect("`owner` is shown to own the kitty; \
             `owner` must have greater than 0 kitties, so transfer cannot cause underflow; \
-            `all_kitty_count` shares the same type as `owned_kitty_count` \
-            and minting ensure there won't ever be more than `max()` kitties, \
-            which means transfer cannot cause an overflow; \
+            `sender` is shown to be able to receive another kitty, \
+            so transfer cannot cause an overflow or exceed the per-account limit; \
             qed");
 
-            // kittyを市場から戻す
-            kitty.price = <T::Balance as As<u64>>::sa(0);
-            <Kitties<T>>::insert(kitty_id, kitty);
-
             // Event発行
             Self::deposit_event(RawEvent::Bought(sender, owner, kitty_id, kitty_price));
 
             Ok(())
         }
 
-        // 交配用関数
-        fn breed_kitty(origin, kitty_id_1: T::Hash, kitty_id_2: T::Hash) -> Result{
+        // 交配を予約する関数
+        // commitmentはsecretのハッシュで、REVEAL_DELAYブロック後にreveal_breedでsecretを公開する
+        fn commit_breed(origin, kitty_id_1: T::Hash, kitty_id_2: T::Hash, commitment: T::Hash) -> Result {
             let sender = ensure_signed(origin)?;
 
-            ensure!(<Kitties<T>>::exists(kitty_id_1), "This cat 1 does not exist");
-            ensure!(<Kitties<T>>::exists(kitty_id_2), "This cat 2 does not exist");
+            // 所有権や親子関係、クールダウンなどの交配ルールを確認する
+            Self::ensure_can_breed(&sender, kitty_id_1, kitty_id_2)?;
 
-            let nonce = <Nonce<T>>::get();
-            let random_hash = (<system::Module<T>>::random_seed(), &sender, nonce)
-                .using_encoded(<T as system::Trait>::Hashing::hash);
+            Self::commit(sender, kitty_id_1, kitty_id_2, None, commitment)
+        }
+
+        // secretを公開して予約した交配を行う関数
+        // DNAはsecretと、コミットした時点では決まっていなかったreveal_atのブロックハッシュから作られる
+        // revealするブロックを選んでも結果は変わらない
+        fn reveal_breed(origin, secret: T::Hash) -> Result {
+            let sender = ensure_signed(origin)?;
 
-            let kitty_1 = Self::kitty(kitty_id_1);
-            let kitty_2 = Self::kitty(kitty_id_2);
+            let commitment = <T as system::Trait>::Hashing::hash_of(&secret);
+            let pending = Self::breed_commitment(&sender, &commitment).ok_or("There is no breeding commitment for this secret")?;
+            ensure!(<system::Module<T>>::block_number() > pending.reveal_at, "This breeding commitment can't be revealed yet");
 
-            let mut final_dna = kitty_1.dna;
-            for (i, (dna_2_element, r)) in kitty_2.dna.as_ref().iter().zip(random_hash.as_ref().iter()).enumerate() {
-                if r % 2 == 0 {
-                    final_dna.as_mut()[i] = *dna_2_element;
+            // コミットしてから状況が変わっていないか、交配ルールを確認し直す
+            let sire_payment = match pending.sire_fee {
+                None => {
+                    Self::ensure_can_breed(&sender, pending.kitty_id_1, pending.kitty_id_2)?;
+                    None
                 }
+                Some(fee) => {
+                    let (sire_owner, current_fee) = Self::ensure_can_use_sire(&sender, pending.kitty_id_1, pending.kitty_id_2)?;
+                    ensure!(current_fee == fee, "The sire fee has changed since the commitment");
+                    Some((sire_owner, fee))
+                }
+            };
+
+            // 交配料を支払う前に、生まれるKittyを受け取れるか確認する
+            let offspring_owner = Self::offspring_owner(&sender, pending.kitty_id_1, pending.kitty_id_2)?;
+            Self::ensure_can_mint(1)?;
+            Self::ensure_can_receive(&offspring_owner)?;
+
+            Self::charge_fee(&sender, Self::breed_fee())?;
+
+            // 確保していた交配料を種親の所有者に支払う
+            if let Some((sire_owner, fee)) = sire_payment {
+                <balances::Module<T> as ReservableCurrency<_>>::repatriate_reserved(&sender, &sire_owner, fee)?;
+                <SireUses<T>>::mutate(pending.kitty_id_2, |uses| *uses = uses.saturating_add(1));
+                Self::deposit_event(RawEvent::SireUsed(sender.clone(), sire_owner, pending.kitty_id_2, fee));
             }
 
-            let new_kitty = Kitty {
-                id: random_hash,
-                dna: final_dna,
-                price: <T::Balance as As<u64>>::sa(0),
-                gen: cmp::max(kitty_1.gen, kitty_2.gen) + 1,
-            };
+            <BreedCommitments<T>>::remove(&sender, &commitment);
 
-            Self::mint(sender, random_hash, new_kitty)?;
+            let random_hash = (<system::Module<T>>::block_hash(pending.reveal_at), secret, &sender, pending.kitty_id_1, pending.kitty_id_2)
+                .using_encoded(<T as system::Trait>::Hashing::hash);
 
-            <Nonce<T>>::mutate(|n| *n += 1);
+            Self::breed(sender, pending.kitty_id_1, pending.kitty_id_2, random_hash)?;
 
             Ok(())
         }
-    }
-}
 
-// mintとTransferを
-impl<T: Trait> Module<T> {
-    fn mint(to: T::AccountId, kitty_id: T::Hash, new_kitty: Kitty<T::Hash, T::Balance>) -> Result {
-        // originをチェックしてメッセージが有効なアカウントで署名されているか確認
-        ensure!(!<KittyOwner<T>>::exists(kitty_id), "Kitty already exists");
-        // KittyCountを取得し、オーバーフローチェックをしてから1インクリメントする
-        let owned_kitty_count = Self::owned_kitty_count(&to);
+        // 自分のKittyを交配料付きで種親として貸し出す関数
+        fn offer_sire(origin, kitty_id: T::Hash, fee: T::Balance) -> Result {
+            let sender = ensure_signed(origin)?;
 
-        let new_owned_kitty_count = owned_kitty_count.checked_add(1)
-        .ok_or("Overflow adding a new kitty count to owned kitty count")?;
+            let owner = Self::owner_of(kitty_id).ok_or("No owner for this kitty")?;
+            ensure!(owner == sender, "You do not own this kitty");
 
-        // all_kitties_countを使って現在のKittyの数をえる
-        let all_kitties_count = Self::all_kitties_count();
+            Self::ensure_not_in_auction(kitty_id)?;
 
-        // Kittyを新しく追加するため、AllKittiysCountを一つ増やすした値を作成する
-        // インクリメント時は必ずchecked_add()を使ってオーバーフローを検知すること
-        let new_all_kitties_count = all_kitties_count.checked_add(1)
-            .ok_or("overflow adding a new kitty to total supply")?;
+            // 生まれたKittyは借りた側のメスの所有者が受け取るため、種親はオスに限る
+            ensure!(Self::kitty(kitty_id).gender == Gender::Male, "Only male kitties can be offered as sires");
 
+            <Kitties<T>>::mutate(kitty_id, |kitty| kitty.sire_fee = Some(fee));
 
+            Self::deposit_event(RawEvent::SireListed(sender, kitty_id, fee));
 
-        //KittyOwnerストレージを使ってKittyの所有権を確認する
-        ensure!(!<KittyOwner<T>>::exists(kitty_id), "Kitty already exists");
+            Ok(())
+        }
 
-        // 作成したKittyをストレージに加えていく
-        <Kitties<T>>::insert(kitty_id, new_kitty);
-        <KittyOwner<T>>::insert(kitty_id, &to);
+        // 種親の貸し出しを取りやめる関数
+        fn delist_sire(origin, kitty_id: T::Hash) -> Result {
+            let sender = ensure_signed(origin)?;
 
-        // GobalKittyTrackingのストレージを更新していく
-        // まずAllKittiesArrayのマッピングリストに登録したKittyを追加
-        <AllKittiesArray<T>>::insert(all_kitties_count, kitty_id);
-        // 新たなKittyCountの値を追加する
-        <AllKittiesCount<T>>::put(new_all_kitties_count);
-        // 逆向きのマッピングリストン追加
-        <AllKittiesIndex<T>>::insert(kitty_id, all_kitties_count);
+            let owner = Self::owner_of(kitty_id).ok_or("No owner for this kitty")?;
+            ensure!(owner == sender, "You do not own this kitty");
 
-        // 所有しているKittyの情報を更新する
-        <OwnedKittiesArray<T>>::insert((to.clone(), owned_kitty_count), kitty_id);
-        <OwnedKittiesCount<T>>::insert(&to, new_owned_kitty_count);
-        <OwnedKittiesIndex<T>>::insert(kitty_id, owned_kitty_count);
+            ensure!(Self::kitty(kitty_id).sire_fee.is_some(), "This kitty is not offered as a sire");
+            <Kitties<T>>::mutate(kitty_id, |kitty| kitty.sire_fee = None);
 
+            Self::deposit_event(RawEvent::SireDelisted(sender, kitty_id));
 
-        // Eventを呼び出す(create時に使ったアドレスとランダムハッシュを渡す)
-        Self::deposit_event(RawEvent::Created(to, kitty_id));
+            Ok(())
+        }
 
-        Ok(())
-    }
+        // 貸し出されている種親と自分のKittyの交配を予約する関数
+        // 交配料はrevealまで確保され、revealされずに失効すると返却される
+        fn commit_breed_with_sire(origin, kitty_id: T::Hash, sire_id: T::Hash, commitment: T::Hash) -> Result {
+            let sender = ensure_signed(origin)?;
 
-    fn transfer_from(from: T::AccountId, to: T::AccountId, kitty_id: T::Hash) -> Result {
-        // Kittyがownerを持っているか確認してから代入
-        let owner = Self::owner_of(kitty_id).ok_or("No owner for this kitty")?;
+            let (_, fee) = Self::ensure_can_use_sire(&sender, kitty_id, sire_id)?;
 
-        // 送信者の所有権を確認
-        ensure!(owner == from, "from account does not owen this kitty");
+            Self::commit(sender, kitty_id, sire_id, Some(fee), commitment)
+        }
 
-        // 移転用の準備
-        let owned_kitty_count_from = Self::owned_kitty_count(&from);
-        let owned_kitty_count_to = Self::owned_kitty_count(&to);
+        // 自分のKittyとの交配を他のアカウントに一度だけ許可する関数
+        fn approve_siring(origin, kitty_id: T::Hash, to: T::AccountId) -> Result {
+            let sender = ensure_signed(origin)?;
 
-        // 事前チェック
-        let new_owned_kitty_count_to = owned_kitty_count_to.checked_add(1)
-            .ok_or("overflow adding a new kitty to total supply")?;
-        let new_owned_kitty_count_from = owned_kitty_count_from.checked_add(1)
-            .ok_or("overflow adding a new kitty to total supply")?;
+            let owner = Self::owner_of(kitty_id).ok_or("No owner for this kitty")?;
+            ensure!(owner == sender, "You do not own this kitty");
 
-        // swap and popでトランスファーを行う
-        let kitty_index = <OwnedKittiesIndex<T>>::get(kitty_id);
-        if kitty_index != new_owned_kitty_count_from {
-            let last_kitty_id = <OwnedKittiesArray<T>>::get((from.clone(), new_owned_kitty_count_from));
-            <OwnedKittiesArray<T>>::insert((from.clone(), kitty_index), last_kitty_id);
-            <OwnedKittiesIndex<T>>::insert(last_kitty_id, kitty_index);
+            <SireAllowedTo<T>>::insert(kitty_id, &to);
+
+            Self::deposit_event(RawEvent::SiringApproved(sender, to, kitty_id));
+
+            Ok(())
         }
 
-        // 結果を記録していく
-        <KittyOwner<T>>::insert(&kitty_id, &to);
-        <OwnedKittiesIndex<T>>::insert(kitty_id, owned_kitty_count_to);
+        // Kittyを焼却してストレージから取り除く関数
+        fn burn_kitty(origin, kitty_id: T::Hash) -> Result {
+            let sender = ensure_signed(origin)?;
 
-        <OwnedKittiesArray<T>>::remove((from.clone(), new_owned_kitty_count_from));
-        <OwnedKittiesArray<T>>::insert((to.clone(), owned_kitty_count_to), kitty_id);
+            let owner = Self::owner_of(kitty_id).ok_or("No owner for this kitty")?;
+            ensure!(owner == sender, "You do not own this kitty");
 
-        <OwnedKittiesCount<T>>::insert(&from, new_owned_kitty_count_from);
-        <OwnedKittiesCount<T>>::insert(&to, new_owned_kitty_count_to);
-        
-        Self::deposit_event(RawEvent::Transferred(from, to, kitty_id));
+            Self::ensure_not_in_auction(kitty_id)?;
 
-        Ok(())
-    }
-}
\ No newline at end of file
+            Self::burn(sender, kitty_id)?;
+
+            Ok(())
+        }
+
+        // Kittyをオークションに出品する関数
+        fn create_auction(origin, kitty_id: T::Hash, reserve: T::Balance, end_block: T::BlockNumber) -> Result {
+            let sender = ensure_signed(origin)?;
+
+            let owner = Self::owner_of(kitty_id).ok_or("No owner for this kitty")?;
+            ensure!(owner == sender, "You do not own this kitty");
+
+            Self::ensure_not_in_auction(kitty_id)?;
+
+            // 固定価格で売りに出されているKittyは出品できない
+            ensure!(Self::kitty(kitty_id).price.is_zero(), "This kitty is already for sale at a fixed price");
+
+            ensure!(end_block > <system::Module<T>>::block_number(), "The auction must end in the future");
+
+            let auction = Auction {
+                seller: sender.clone(),
+                reserve,
+                end_block,
+                highest_bid: <T::Balance as As<u64>>::sa(0),
+                highest_bidder: None,
+            };
+
+            <Auctions<T>>::insert(kitty_id, auction);
+            <AuctionsEndingAt<T>>::mutate(end_block, |kitty_ids| kitty_ids.push(kitty_id));
+
+            Self::deposit_event(RawEvent::AuctionCreated(sender, kitty_id, reserve, end_block));
+
+            Ok(())
+        }
+
+        // オークションに入札する関数
+        fn bid(origin, kitty_id: T::Hash, amount: T::Balance) -> Result {
+            let sender = ensure_signed(origin)?;
+
+            let mut auction = Self::auction_of(kitty_id).ok_or("This kitty is not in an auction")?;
+
+            ensure!(<system::Module<T>>::block_number() <= auction.end_block, "This auction has already ended");
+            ensure!(auction.seller != sender, "You can't bid on your own kitty");
+            ensure!(amount >= auction.reserve, "The bid is below the reserve price");
+            if auction.highest_bidder.is_some() {
+                ensure!(amount > auction.highest_bid, "The bid must be higher than the current highest bid");
+            }
+
+            // 入札額をReservableCurrencyで確保する
+            <balances::Module<T> as ReservableCurrency<_>>::reserve(&sender, amount)?;
+
+            // 以前の最高入札者に資金を返却する
+            if let Some(previous_bidder) = auction.highest_bidder.take() {
+                <balances::Module<T> as ReservableCurrency<_>>::unreserve(&previous_bidder, auction.highest_bid);
+            }
+
+            auction.highest_bid = amount;
+            auction.highest_bidder = Some(sender.clone());
+            <Auctions<T>>::insert(kitty_id, auction);
+
+            Self::deposit_event(RawEvent::BidPlaced(sender, kitty_id, amount));
+
+            Ok(())
+        }
+
+        // 開始価格から終了価格まで、指定したブロック数をかけて価格が下がる販売を開始する関数
+        fn create_dutch_auction(origin, kitty_id: T::Hash, start_price: T::Balance, end_price: T::Balance, duration: T::BlockNumber) -> Result {
+            let sender = ensure_signed(origin)?;
+
+            let owner = Self::owner_of(kitty_id).ok_or("No owner for this kitty")?;
+            ensure!(owner == sender, "You do not own this kitty");
+
+            Self::ensure_not_in_auction(kitty_id)?;
+
+            ensure!(!end_price.is_zero(), "The end price must be greater than zero");
+            ensure!(start_price >= end_price, "The start price must not be lower than the end price");
+            ensure!(!duration.is_zero(), "The duration must be at least one block");
+
+            let auction = DutchAuction {
+                start_price,
+                end_price,
+                start_block: <system::Module<T>>::block_number(),
+                duration,
+            };
+
+            // 開始価格で売りに出し、いずれ下がりきる終了価格の価格帯に登録する
+            Self::set_listing_price(kitty_id, start_price);
+            Self::remove_from_sale_index(kitty_id);
+            Self::add_to_sale_index(kitty_id, end_price);
+            <DutchAuctions<T>>::insert(kitty_id, auction);
+
+            Self::deposit_event(RawEvent::DutchAuctionCreated(sender, kitty_id, start_price, end_price, duration));
+
+            Ok(())
+        }
+
+        // 売りに出されていないKittyに購入オファーを出す関数
+        fn make_offer(origin, kitty_id: T::Hash, amount: T::Balance, expires_at: T::BlockNumber) -> Result {
+            let sender = ensure_signed(origin)?;
+
+            let owner = Self::owner_of(kitty_id).ok_or("No owner for this kitty")?;
+            ensure!(owner != sender, "You can't make an offer on your own kitty");
+
+            ensure!(amount >= <T::Balance as As<u64>>::sa(T::MIN_OFFER_AMOUNT), "The offer is below the minimum amount");
+            ensure!(expires_at > <system::Module<T>>::block_number(), "The offer must expire in the future");
+            ensure!(Self::offer_of(&kitty_id, &sender).is_none(), "You already have an offer on this kitty");
+
+            // オファーが上限まで出ている場合は、最も低いオファーを上回れば入れ替わる
+            let bidders = Self::offer_bidders(kitty_id);
+            let outbid = if bidders.len() >= T::MAX_OFFERS_PER_KITTY {
+                let lowest = bidders.into_iter()
+                    .filter_map(|bidder| Self::offer_of(&kitty_id, &bidder).map(|offer| (bidder, offer.amount)))
+                    .min_by_key(|(_, amount)| *amount);
+                match lowest {
+                    Some((bidder, lowest_amount)) if amount > lowest_amount => Some((bidder, lowest_amount)),
+                    _ => return Err("The offer must be higher than the lowest offer on this kitty"),
+                }
+            } else {
+                None
+            };
+
+            // オファー額をReservableCurrencyで確保する
+            <balances::Module<T> as ReservableCurrency<_>>::reserve(&sender, amount)?;
+
+            // 入れ替わったオファーの資金を返却する
+            if let Some((bidder, lowest_amount)) = outbid {
+                Self::remove_offer(kitty_id, &bidder);
+                <balances::Module<T> as ReservableCurrency<_>>::unreserve(&bidder, lowest_amount);
+                Self::deposit_event(RawEvent::OfferOutbid(bidder, kitty_id));
+            }
+
+            <Offers<T>>::insert(&kitty_id, &sender, &Offer { amount, expires_at });
+            <OfferBidders<T>>::mutate(kitty_id, |bidders| bidders.push(sender.clone()));
+            <OffersExpiringAt<T>>::mutate(expires_at, |offers| offers.push((kitty_id, sender.clone())));
+
+            Self::deposit_event(RawEvent::OfferMade(sender, kitty_id, amount, expires_at));
+
+            Ok(())
+        }
+
+        // 所有者がオファーを受け入れてKittyを売却する関数
+        fn accept_offer(origin, kitty_id: T::Hash, bidder: T::AccountId) -> Result {
+            let sender = ensure_signed(origin)?;
+
+            let owner = Self::owner_of(kitty_id).ok_or("No owner for this kitty")?;
+            ensure!(owner == sender, "You do not own this kitty");
+
+            Self::ensure_not_in_auction(kitty_id)?;
+
+            let offer = Self::offer_of(&kitty_id, &bidder).ok_or("There is no offer from this account")?;
+            ensure!(<system::Module<T>>::block_number() <= offer.expires_at, "This offer has expired");
+
+            // 資金を動かす前に移転できることを確認する
+            Self::ensure_can_receive(&bidder)?;
+
+            // 確保していたオファー額を所有者と作成者へ移す
+            Self::pay_sale(&bidder, &owner, kitty_id, offer.amount, true)?;
+            Self::remove_offer(kitty_id, &bidder);
+
+            // 固定価格での販売も終了させる
+            Self::set_listing_price(kitty_id, Zero::zero());
+            <DutchAuctions<T>>::remove(kitty_id);
+
+            Self::transfer_from(owner.clone(), bidder.clone(), kitty_id)
+                .expect("`owner` is shown to own the kitty; \
+                `bidder` is shown to be able to receive another kitty; \
+                qed");
+
+            Self::deposit_event(RawEvent::OfferAccepted(owner, bidder, kitty_id, offer.amount));
+
+            Ok(())
+        }
+
+        // 自分のオファーを取り下げて資金を返却する関数
+        fn withdraw_offer(origin, kitty_id: T::Hash) -> Result {
+            let sender = ensure_signed(origin)?;
+
+            let offer = Self::offer_of(&kitty_id, &sender).ok_or("You have no offer on this kitty")?;
+
+            Self::remove_offer(kitty_id, &sender);
+            <balances::Module<T> as ReservableCurrency<_>>::unreserve(&sender, offer.amount);
+
+            Self::deposit_event(RawEvent::OfferWithdrawn(sender, kitty_id));
+
+            Ok(())
+        }
+
+        // ロイヤリティの割合を設定する関数(root専用)
+        fn set_royalty_rate(origin, rate: Permill) -> Result {
+            ensure_root(origin)?;
+
+            <RoyaltyRate<T>>::put(rate);
+
+            Self::deposit_event(RawEvent::RoyaltyRateSet(rate));
+
+            Ok(())
+        }
+
+        // Kittyに名前を付ける関数
+        // 名前は全Kittyで一意で、バイト数に応じたデポジットが確保される
+        fn set_name(origin, kitty_id: T::Hash, name: Vec<u8>) -> Result {
+            let sender = ensure_signed(origin)?;
+
+            let owner = Self::owner_of(kitty_id).ok_or("No owner for this kitty")?;
+            ensure!(owner == sender, "You do not own this kitty");
+
+            ensure!(name.len() >= T::MIN_NAME_LENGTH, "This name is too short");
+            ensure!(name.len() <= T::MAX_NAME_LENGTH, "This name is too long");
+            ensure!(Self::kitty_by_name(&name).map_or(true, |id| id == kitty_id), "This name is already taken");
+
+            let uri_length = Self::metadata_of(kitty_id).map_or(0, |metadata| metadata.uri.len());
+            Self::update_deposit(&sender, kitty_id, name.len() + uri_length)?;
+
+            if let Some(old_name) = Self::name_of(kitty_id) {
+                <KittyByName<T>>::remove(old_name);
+            }
+            <KittyByName<T>>::insert(&name, kitty_id);
+            <KittyNames<T>>::insert(kitty_id, name);
+
+            Self::deposit_event(RawEvent::NameSet(sender, kitty_id));
+
+            Ok(())
+        }
+
+        // 名前を消してデポジットを返却する関数
+        fn clear_name(origin, kitty_id: T::Hash) -> Result {
+            let sender = ensure_signed(origin)?;
+
+            let owner = Self::owner_of(kitty_id).ok_or("No owner for this kitty")?;
+            ensure!(owner == sender, "You do not own this kitty");
+
+            let name = Self::name_of(kitty_id).ok_or("This kitty has no name")?;
+
+            let uri_length = Self::metadata_of(kitty_id).map_or(0, |metadata| metadata.uri.len());
+            Self::update_deposit(&sender, kitty_id, uri_length)?;
+
+            <KittyByName<T>>::remove(name);
+            <KittyNames<T>>::remove(kitty_id);
+
+            Self::deposit_event(RawEvent::NameCleared(sender, kitty_id));
+
+            Ok(())
+        }
+
+        // KittyにメタデータのURIと内容のハッシュを設定する関数
+        // URIのバイト数に応じたデポジットが確保される
+        fn set_metadata(origin, kitty_id: T::Hash, uri: Vec<u8>, content_hash: T::Hash) -> Result {
+            let sender = ensure_signed(origin)?;
+
+            let owner = Self::owner_of(kitty_id).ok_or("No owner for this kitty")?;
+            ensure!(owner == sender, "You do not own this kitty");
+
+            ensure!(!uri.is_empty(), "The metadata URI is empty");
+            ensure!(uri.len() <= T::MAX_METADATA_URI_LENGTH, "The metadata URI is too long");
+
+            let name_length = Self::name_of(kitty_id).map_or(0, |name| name.len());
+            Self::update_deposit(&sender, kitty_id, name_length + uri.len())?;
+
+            <KittyMetadataOf<T>>::insert(kitty_id, KittyMetadata { uri, content_hash });
+
+            Self::deposit_event(RawEvent::MetadataSet(sender, kitty_id));
+
+            Ok(())
+        }
+
+        // メタデータを消してデポジットを返却する関数
+        fn clear_metadata(origin, kitty_id: T::Hash) -> Result {
+            let sender = ensure_signed(origin)?;
+
+            let owner = Self::owner_of(kitty_id).ok_or("No owner for this kitty")?;
+            ensure!(owner == sender, "You do not own this kitty");
+
+            ensure!(<KittyMetadataOf<T>>::exists(kitty_id), "This kitty has no metadata");
+
+            let name_length = Self::name_of(kitty_id).map_or(0, |name| name.len());
+            Self::update_deposit(&sender, kitty_id, name_length)?;
+
+            <KittyMetadataOf<T>>::remove(kitty_id);
+
+            Self::deposit_event(RawEvent::MetadataCleared(sender, kitty_id));
+
+            Ok(())
+        }
+
+        // Kittyの総数の上限を設定する関数(root専用、Noneで上限なし)
+        // 既に上限を超えている場合、新しいmintだけが止まる
+        fn set_max_supply(origin, max_supply: Option<u64>) -> Result {
+            ensure_root(origin)?;
+
+            <MaxSupply<T>>::put(max_supply);
+
+            Self::deposit_event(RawEvent::MaxSupplySet(max_supply));
+
+            Ok(())
+        }
+
+        // 1アカウントが所有できるKittyの数の上限を設定する関数(root専用、Noneで上限なし)
+        fn set_max_owned_per_account(origin, max_owned: Option<u64>) -> Result {
+            ensure_root(origin)?;
+
+            <MaxOwnedPerAccount<T>>::put(max_owned);
+
+            Self::deposit_event(RawEvent::MaxOwnedPerAccountSet(max_owned));
+
+            Ok(())
+        }
+
+        // reveal_createの手数料を設定する関数(root専用)
+        fn set_mint_fee(origin, fee: T::Balance) -> Result {
+            ensure_root(origin)?;
+
+            <MintFee<T>>::put(fee);
+
+            Self::deposit_event(RawEvent::MintFeeSet(fee));
+
+            Ok(())
+        }
+
+        // 交配の手数料を設定する関数(root専用)
+        fn set_breed_fee(origin, fee: T::Balance) -> Result {
+            ensure_root(origin)?;
+
+            <BreedFee<T>>::put(fee);
+
+            Self::deposit_event(RawEvent::BreedFeeSet(fee));
+
+            Ok(())
+        }
+
+        // 手数料の行き先を設定する関数(root専用)
+        fn set_fee_destination(origin, destination: FeeDestination<T::AccountId>) -> Result {
+            ensure_root(origin)?;
+
+            // 作成者が分からないと手数料が黙って焼却されるため受け付けない
+            if let FeeDestination::BlockAuthor = destination {
+                ensure!(T::BlockAuthor::block_author().is_some(), "This runtime can't find the block author");
+            }
+
+            <FeeDestinationOf<T>>::put(&destination);
+
+            Self::deposit_event(RawEvent::FeeDestinationSet(destination));
+
+            Ok(())
+        }
+
+        // 交配で生まれたKittyをメスの所有者に渡すかどうかを設定する関数(root専用)
+        fn set_offspring_to_female_owner(origin, enabled: bool) -> Result {
+            ensure_root(origin)?;
+
+            <OffspringToFemaleOwner<T>>::put(enabled);
+
+            Self::deposit_event(RawEvent::OffspringToFemaleOwnerSet(enabled));
+
+            Ok(())
+        }
+
+        // 入札が無いオークションを取り消す関数
+        fn cancel_auction(origin, kitty_id: T::Hash) -> Result {
+            let sender = ensure_signed(origin)?;
+
+            let auction = Self::auction_of(kitty_id).ok_or("This kitty is not in an auction")?;
+            ensure!(auction.seller == sender, "You are not the seller of this auction");
+            ensure!(auction.highest_bidder.is_none(), "You can't cancel an auction that has bids");
+
+            <Auctions<T>>::remove(kitty_id);
+            <AuctionsEndingAt<T>>::mutate(auction.end_block, |kitty_ids| kitty_ids.retain(|id| *id != kitty_id));
+
+            Self::deposit_event(RawEvent::AuctionCancelled(sender, kitty_id));
+
+            Ok(())
+        }
+    }
+}
+
+// mintとTransferを
+impl<T: Trait> Module<T> {
+    fn mint(
+        to: T::AccountId,
+        kitty_id: T::Hash,
+        new_kitty: Kitty<T::Hash, T::Balance, T::BlockNumber>,
+        parents: Option<(T::Hash, T::Hash)>,
+    ) -> Result {
+        // originをチェックしてメッセージが有効なアカウントで署名されているか確認
+        ensure!(!<KittyOwner<T>>::exists(kitty_id), "Kitty already exists");
+        // KittyCountを取得し、オーバーフローチェックをしてから1インクリメントする
+        let owned_kitty_count = Self::owned_kitty_count(&to);
+
+        let new_owned_kitty_count = owned_kitty_count.checked_add(1)
+        .ok_or("Overflow adding a new kitty count to owned kitty count")?;
+
+        // all_kitties_countを使って現在のKittyの数をえる
+        let all_kitties_count = Self::all_kitties_count();
+
+        // Kittyを新しく追加するため、AllKittiysCountを一つ増やすした値を作成する
+        // インクリメント時は必ずchecked_add()を使ってオーバーフローを検知すること
+        let new_all_kitties_count = all_kitties_count.checked_add(1)
+            .ok_or("overflow adding a new kitty to total supply")?;
+
+        // rootが設定した総数と所有数の上限を確認する
+        Self::ensure_can_mint(1)?;
+        Self::ensure_can_receive(&to)?;
+
+        // 交配で生まれた場合は親の子供の数も事前にチェックする
+        if let Some((parent_1, parent_2)) = parents {
+            Self::children_count(parent_1).checked_add(1)
+                .ok_or("overflow adding a new child to parent 1")?;
+            Self::children_count(parent_2).checked_add(1)
+                .ok_or("overflow adding a new child to parent 2")?;
+        }
+
+        //KittyOwnerストレージを使ってKittyの所有権を確認する
+        ensure!(!<KittyOwner<T>>::exists(kitty_id), "Kitty already exists");
+
+        // 作成したKittyをストレージに加えていく
+        <Kitties<T>>::insert(kitty_id, new_kitty);
+        <KittyOwner<T>>::insert(kitty_id, &to);
+        <KittyCreator<T>>::insert(kitty_id, &to);
+
+        // GobalKittyTrackingのストレージを更新していく
+        // まずAllKittiesArrayのマッピングリストに登録したKittyを追加
+        <AllKittiesArray<T>>::insert(all_kitties_count, kitty_id);
+        // 新たなKittyCountの値を追加する
+        <AllKittiesCount<T>>::put(new_all_kitties_count);
+        // 逆向きのマッピングリストン追加
+        <AllKittiesIndex<T>>::insert(kitty_id, all_kitties_count);
+
+        // 所有しているKittyの情報を更新する
+        <OwnedKittiesArray<T>>::insert((to.clone(), owned_kitty_count), kitty_id);
+        <OwnedKittiesCount<T>>::insert(&to, new_owned_kitty_count);
+        <OwnedKittiesIndex<T>>::insert(kitty_id, owned_kitty_count);
+
+
+        // Eventを呼び出す(create時に使ったアドレスとランダムハッシュを渡す)
+        Self::deposit_event(RawEvent::Created(to.clone(), kitty_id));
+
+        // 家系図を記録する
+        if let Some((parent_1, parent_2)) = parents {
+            <KittyParents<T>>::insert(kitty_id, (parent_1, parent_2));
+            Self::add_child(parent_1, kitty_id);
+            Self::add_child(parent_2, kitty_id);
+
+            Self::deposit_event(RawEvent::Bred(to, kitty_id, parent_1, parent_2));
+        }
+
+        Ok(())
+    }
+
+    // 手数料をwhoから徴収して設定された行き先に送る
+    fn charge_fee(who: &T::AccountId, fee: T::Balance) -> Result {
+        if fee.is_zero() {
+            return Ok(());
+        }
+
+        let destination = match Self::fee_destination() {
+            FeeDestination::Burn => None,
+            FeeDestination::Account(account) => Some(account),
+            // 設定時に作成者が分かることは確認しているが、分からないブロックでは焼却する
+            FeeDestination::BlockAuthor => T::BlockAuthor::block_author(),
+        };
+
+        match destination {
+            Some(destination) => <balances::Module<T> as Currency<_>>::transfer(who, &destination, fee)?,
+            // 引き出した残高を捨てると総発行量からも取り除かれる
+            None => drop(<balances::Module<T> as Currency<_>>::withdraw(
+                who, fee, WithdrawReason::Fee, ExistenceRequirement::KeepAlive)?),
+        }
+
+        Self::deposit_event(RawEvent::FeePaid(who.clone(), fee));
+
+        Ok(())
+    }
+
+    // ランダムハッシュから第0世代のKittyを作ってownerにmintする
+    fn create_gen0_kitty(owner: T::AccountId, random_hash: T::Hash) -> Result {
+        // 各遺伝子の対立遺伝子は、値の小さい優性のものが先に来るよう並べる
+        let mut dna = random_hash;
+        genetics::order_alleles(dna.as_mut());
+
+        // Kittyオブジェクトを使ってnew_kittyを作成する
+        // new_kittyの中身にRuntimeストレージのデータを初期化↓データを入れる
+        let new_kitty = Kitty {
+            id: random_hash,
+            dna,
+            price: <T::Balance as As<u64>>::sa(0),
+            gen: 0,
+            ready_at: Zero::zero(),
+            sire_fee: None,
+            gender: genetics::gender(dna.as_ref()),
+        };
+
+        // リファクタリングしたMintを使う
+        Self::mint(owner, random_hash, new_kitty, None)
+    }
+
+    fn add_child(parent: T::Hash, child: T::Hash) {
+        let children_count = Self::children_count(parent);
+
+        <KittyChildrenArray<T>>::insert((parent, children_count), child);
+        <KittyChildrenCount<T>>::insert(parent, children_count + 1);
+    }
+
+    // Kittyの子供を全て返す(焼却されたKittyも家系図として残る)
+    pub fn children_of(kitty_id: T::Hash) -> Vec<T::Hash> {
+        (0..Self::children_count(kitty_id))
+            .map(|i| Self::child_of_kitty_by_index((kitty_id, i)))
+            .collect()
+    }
+
+    fn transfer_from(from: T::AccountId, to: T::AccountId, kitty_id: T::Hash) -> Result {
+        // Kittyがownerを持っているか確認してから代入
+        let owner = Self::owner_of(kitty_id).ok_or("No owner for this kitty")?;
+
+        // 送信者の所有権を確認
+        ensure!(owner == from, "from account does not owen this kitty");
+
+        // 事前チェック(自分自身への移転では所有数は増えない)
+        if from != to {
+            Self::ensure_can_receive(&to)?;
+        }
+
+        // swap and popで送信者の所有リストから取り除く
+        Self::remove_owned_kitty(&from, kitty_id)?;
+
+        // 自分自身への移転でも正しく数えるため、取り除いた後の値を使う
+        let owned_kitty_count_to = Self::owned_kitty_count(&to);
+        let new_owned_kitty_count_to = owned_kitty_count_to + 1;
+
+        // 結果を記録していく
+        <KittyOwner<T>>::insert(&kitty_id, &to);
+        <OwnedKittiesIndex<T>>::insert(kitty_id, owned_kitty_count_to);
+        <OwnedKittiesArray<T>>::insert((to.clone(), owned_kitty_count_to), kitty_id);
+        <OwnedKittiesCount<T>>::insert(&to, new_owned_kitty_count_to);
+
+        // 所有者が変わったので残っているオファーは全て返却し、移転の承認と交配許可、種親の貸し出しも取り消す
+        Self::clear_offers(kitty_id);
+        <KittyApprovals<T>>::remove(kitty_id);
+        <SireAllowedTo<T>>::remove(kitty_id);
+        <Kitties<T>>::mutate(kitty_id, |kitty| kitty.sire_fee = None);
+
+        // 売りに出されたままであれば販売を終了させる
+        if !Self::kitty(kitty_id).price.is_zero() {
+            Self::set_listing_price(kitty_id, Zero::zero());
+            <DutchAuctions<T>>::remove(kitty_id);
+            Self::deposit_event(RawEvent::Delisted(from.clone(), kitty_id));
+        }
+
+        Self::deposit_event(RawEvent::Transferred(from, to, kitty_id));
+
+        Ok(())
+    }
+
+    // ランタイムAPI向け: 存在する場合のみKittyを返す
+    pub fn kitty_details(kitty_id: T::Hash) -> Option<Kitty<T::Hash, T::Balance, T::BlockNumber>> {
+        if <Kitties<T>>::exists(kitty_id) {
+            Some(Self::kitty(kitty_id))
+        } else {
+            None
+        }
+    }
+
+    // ランタイムAPI向け: ownerが所有するKittyをstart番目からlimit匹まで返す
+    pub fn kitties_of_owner(owner: &T::AccountId, start: u64, limit: u64) -> Vec<Kitty<T::Hash, T::Balance, T::BlockNumber>> {
+        let end = cmp::min(start.saturating_add(limit), Self::owned_kitty_count(owner));
+
+        (start..end)
+            .map(|i| Self::kitty(Self::kitty_of_owner_by_index((owner.clone(), i))))
+            .collect()
+    }
+
+    // ランタイムAPI向け: 売りに出されているKittyをstart番目からlimit匹まで返す
+    // 安い価格帯から順に並ぶ(価格帯の中の順番は決まっていない。ダッチオークションは終了価格の価格帯に入る)
+    pub fn kitties_for_sale(start: u64, limit: u64) -> Vec<Kitty<T::Hash, T::Balance, T::BlockNumber>> {
+        Self::kitties_for_sale_in_buckets(PRICE_BUCKETS - 1, start, limit)
+            .into_iter()
+            .map(Self::kitty)
+            .collect()
+    }
+
+    // ランタイムAPI向け: 現在の販売価格がmax_price以下のKittyをstart番目からlimit匹まで返す
+    // ダッチオークションは終了価格の価格帯に入っていて、現在の価格はそれより高いことがあるため、
+    // max_price以下の価格帯のKittyを順に読んで現在の価格で絞り込む
+    pub fn kitties_for_sale_below(max_price: T::Balance, start: u64, limit: u64) -> Vec<Kitty<T::Hash, T::Balance, T::BlockNumber>> {
+        if max_price.is_zero() {
+            return Vec::new();
+        }
+
+        let last_bucket = Self::price_bucket(max_price);
+        (0..=last_bucket)
+            .flat_map(|bucket| (0..Self::for_sale_bucket_count(bucket))
+                .map(move |index| Self::for_sale_by_bucket_index((bucket, index))))
+            .filter(|kitty_id| Self::current_price(*kitty_id) <= max_price)
+            .skip(start as usize)
+            .take(limit as usize)
+            .map(Self::kitty)
+            .collect()
+    }
+
+    // 価格帯0からlast_bucketまでのKittyのIDをstart番目からlimit匹まで返す
+    // 件数の分かっている価格帯はまとめて読み飛ばす
+    fn kitties_for_sale_in_buckets(last_bucket: u32, start: u64, limit: u64) -> Vec<T::Hash> {
+        let mut skip = start;
+        let mut kitty_ids = Vec::new();
+
+        for bucket in 0..=last_bucket {
+            let count = Self::for_sale_bucket_count(bucket);
+            if skip >= count {
+                skip -= count;
+                continue;
+            }
+
+            for index in skip..count {
+                if kitty_ids.len() as u64 >= limit {
+                    return kitty_ids;
+                }
+                kitty_ids.push(Self::for_sale_by_bucket_index((bucket, index)));
+            }
+            skip = 0;
+        }
+
+        kitty_ids
+    }
+
+    // 価格の属する価格帯(価格のlog2の切り捨て)
+    fn price_bucket(price: T::Balance) -> u32 {
+        let two = <T::Balance as As<u64>>::sa(2);
+        let mut remaining = price;
+        let mut bucket = 0;
+        while remaining >= two {
+            remaining = remaining / two;
+            bucket += 1;
+        }
+        cmp::min(bucket, PRICE_BUCKETS - 1)
+    }
+
+    // 販売価格を設定し、売りに出されているKittyのインデックスを合わせる(ゼロは販売終了)
+    // 期限は取り消されるので、期限付きの場合は呼び出し側で設定し直す
+    fn set_listing_price(kitty_id: T::Hash, price: T::Balance) {
+        Self::remove_from_sale_index(kitty_id);
+        <ListingExpiry<T>>::remove(kitty_id);
+        <Kitties<T>>::mutate(kitty_id, |kitty| kitty.price = price);
+        if !price.is_zero() {
+            Self::add_to_sale_index(kitty_id, price);
+        }
+    }
+
+    // 期限を迎えた販売をMAX_LISTING_EXPIRIES_PER_BLOCK件まで終了させ、残りは次のブロックの先頭に回す
+    fn expire_listings(now: T::BlockNumber) {
+        let mut kitty_ids = <ListingsExpiringAt<T>>::take(now);
+        if kitty_ids.len() > T::MAX_LISTING_EXPIRIES_PER_BLOCK {
+            let mut carried = kitty_ids.split_off(T::MAX_LISTING_EXPIRIES_PER_BLOCK);
+            <ListingsExpiringAt<T>>::mutate(now + <T::BlockNumber as As<u64>>::sa(1), |next| {
+                carried.extend(next.drain(..));
+                *next = carried;
+            });
+        }
+
+        for kitty_id in kitty_ids {
+            // 期限が変わった、または既に販売が終わったKittyは飛ばす
+            match Self::listing_expires_at(kitty_id) {
+                Some(expires_at) if expires_at <= now => {
+                    Self::set_listing_price(kitty_id, Zero::zero());
+                    if let Some(owner) = Self::owner_of(kitty_id) {
+                        Self::deposit_event(RawEvent::ListingExpired(owner, kitty_id));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn add_to_sale_index(kitty_id: T::Hash, price: T::Balance) {
+        let bucket = Self::price_bucket(price);
+        let index = Self::for_sale_bucket_count(bucket);
+
+        <ForSaleArray<T>>::insert((bucket, index), kitty_id);
+        <ForSaleBucketCount<T>>::insert(bucket, index + 1);
+        <ForSalePosition<T>>::insert(kitty_id, (bucket, index));
+        <ForSaleCount<T>>::mutate(|count| *count += 1);
+    }
+
+    // 価格帯のリストからswap and popで取り除く(売りに出されていなければ何もしない)
+    fn remove_from_sale_index(kitty_id: T::Hash) {
+        let (bucket, index) = match <ForSalePosition<T>>::take(kitty_id) {
+            Some(position) => position,
+            None => return,
+        };
+
+        let last_index = Self::for_sale_bucket_count(bucket) - 1;
+        if index != last_index {
+            let last_kitty_id = Self::for_sale_by_bucket_index((bucket, last_index));
+            <ForSaleArray<T>>::insert((bucket, index), last_kitty_id);
+            <ForSalePosition<T>>::insert(last_kitty_id, (bucket, index));
+        }
+        <ForSaleArray<T>>::remove((bucket, last_index));
+        <ForSaleBucketCount<T>>::insert(bucket, last_index);
+        <ForSaleCount<T>>::mutate(|count| *count -= 1);
+    }
+
+    // DNAをデコードした見た目の特徴を返す
+    pub fn traits_of(kitty_id: T::Hash) -> Option<KittyTraits> {
+        Self::kitty_details(kitty_id).map(|kitty| genetics::decode(kitty.dna.as_ref()))
+    }
+
+    // ランタイムAPI向け: 現在のKittyの総数
+    pub fn total_supply() -> u64 {
+        Self::all_kitties_count()
+    }
+
+    // 現在のブロックでの販売価格を返す(売りに出されていなければ0)
+    pub fn current_price(kitty_id: T::Hash) -> T::Balance {
+        let auction = match Self::dutch_auction_of(kitty_id) {
+            Some(auction) => auction,
+            None => return Self::kitty(kitty_id).price,
+        };
+
+        let elapsed = <system::Module<T>>::block_number() - auction.start_block;
+        if elapsed >= auction.duration {
+            return auction.end_price;
+        }
+
+        // 経過ブロック数に比例して値下げする(オーバーフローしないよう商と余りに分けて計算)
+        let elapsed = <T::Balance as As<u64>>::sa(elapsed.as_());
+        let duration = <T::Balance as As<u64>>::sa(auction.duration.as_());
+        let price_range = auction.start_price - auction.end_price;
+        let price_drop = price_range / duration * elapsed + price_range % duration * elapsed / duration;
+
+        auction.start_price - price_drop
+    }
+
+    // 今コミットした場合にrevealできるようになるブロックと、コミットメントが失効するブロック
+    fn reveal_window() -> (T::BlockNumber, T::BlockNumber) {
+        let now = <system::Module<T>>::block_number();
+        let reveal_at = now + <T::BlockNumber as As<u64>>::sa(T::REVEAL_DELAY);
+        let expires_at = reveal_at + <T::BlockNumber as As<u64>>::sa(T::REVEAL_PERIOD);
+
+        (reveal_at, expires_at)
+    }
+
+    // 交配のコミットメントを保存する(交配ルールの確認は呼び出し側で行う)
+    fn commit(
+        sender: T::AccountId,
+        kitty_id_1: T::Hash,
+        kitty_id_2: T::Hash,
+        sire_fee: Option<T::Balance>,
+        commitment: T::Hash,
+    ) -> Result {
+        ensure!(Self::breed_commitment(&sender, &commitment).is_none(), "This commitment is already in use");
+
+        let (reveal_at, expires_at) = Self::reveal_window();
+
+        if let Some(fee) = sire_fee {
+            <balances::Module<T> as ReservableCurrency<_>>::reserve(&sender, fee)?;
+        }
+
+        let pending = BreedCommitment {
+            kitty_id_1,
+            kitty_id_2,
+            sire_fee,
+            reveal_at,
+            expires_at,
+        };
+
+        <BreedCommitments<T>>::insert(&sender, &commitment, &pending);
+        <BreedCommitmentsExpiringAt<T>>::mutate(expires_at, |commitments| commitments.push((sender.clone(), commitment)));
+
+        Self::deposit_event(RawEvent::BreedCommitted(sender, commitment, reveal_at));
+
+        Ok(())
+    }
+
+    // 2匹の遺伝子を掛け合わせてsenderのKittyを新たに作る(交配ルールの確認は呼び出し側で行う)
+    fn breed(sender: T::AccountId, kitty_id_1: T::Hash, kitty_id_2: T::Hash, random_hash: T::Hash) -> Result {
+        let kitty_1 = Self::kitty(kitty_id_1);
+        let kitty_2 = Self::kitty(kitty_id_2);
+
+        // 優性・劣性の対立遺伝子を両親から1つずつ受け継ぎ、条件が揃えば突然変異する
+        let mut final_dna = kitty_1.dna;
+        genetics::inherit(
+            kitty_1.dna.as_ref(),
+            kitty_2.dna.as_ref(),
+            random_hash.as_ref(),
+            T::MUTATION_RATES,
+            final_dna.as_mut(),
+        );
+
+        let new_kitty = Kitty {
+            id: random_hash,
+            dna: final_dna,
+            price: <T::Balance as As<u64>>::sa(0),
+            gen: cmp::max(kitty_1.gen, kitty_2.gen) + 1,
+            ready_at: Zero::zero(),
+            sire_fee: None,
+            gender: genetics::gender(final_dna.as_ref()),
+        };
+
+        let to = Self::offspring_owner(&sender, kitty_id_1, kitty_id_2)?;
+
+        Self::mint(to, random_hash, new_kitty, Some((kitty_id_1, kitty_id_2)))?;
+
+        // 他人のKittyに対する交配許可は一度使うと消える
+        for kitty_id in &[kitty_id_1, kitty_id_2] {
+            if Self::owner_of(kitty_id) != Some(sender.clone()) {
+                <SireAllowedTo<T>>::remove(kitty_id);
+            }
+        }
+
+        // 両親に世代に応じたクールダウンを設定する
+        let now = <system::Module<T>>::block_number();
+        Self::start_cooldown(kitty_id_1, now);
+        Self::start_cooldown(kitty_id_2, now);
+
+        Ok(())
+    }
+
+    // 交配のルールを確認する
+    // 両方を所有しているか、片方を所有してもう片方の交配許可を持っている必要がある
+    fn ensure_can_breed(sender: &T::AccountId, kitty_id_1: T::Hash, kitty_id_2: T::Hash) -> Result {
+        ensure!(kitty_id_1 != kitty_id_2, "A kitty can't breed with itself");
+
+        let owner_1 = Self::owner_of(kitty_id_1).ok_or("This cat 1 does not exist")?;
+        let owner_2 = Self::owner_of(kitty_id_2).ok_or("This cat 2 does not exist")?;
+
+        ensure!(owner_1 == *sender || owner_2 == *sender, "You do not own either of these cats");
+        if owner_1 != *sender {
+            ensure!(Self::sire_allowed_to(kitty_id_1).as_ref() == Some(sender),
+                "You do not own cat 1 and have no permission to breed with it");
+        }
+        if owner_2 != *sender {
+            ensure!(Self::sire_allowed_to(kitty_id_2).as_ref() == Some(sender),
+                "You do not own cat 2 and have no permission to breed with it");
+        }
+
+        Self::ensure_breeding_pair(kitty_id_1, kitty_id_2)
+    }
+
+    // 生まれるKittyの所有者
+    // 設定によっては、交配したアカウントではなくメスの所有者になる
+    fn offspring_owner(
+        sender: &T::AccountId,
+        kitty_id_1: T::Hash,
+        kitty_id_2: T::Hash,
+    ) -> rstd::result::Result<T::AccountId, &'static str> {
+        if Self::offspring_to_female_owner() {
+            let female = if Self::kitty(kitty_id_1).gender == Gender::Female { kitty_id_1 } else { kitty_id_2 };
+            Self::owner_of(female).ok_or("No owner for this kitty")
+        } else {
+            Ok(sender.clone())
+        }
+    }
+
+    // 種親を借りて交配するルールを確認し、種親の所有者と交配料を返す
+    fn ensure_can_use_sire(
+        sender: &T::AccountId,
+        kitty_id: T::Hash,
+        sire_id: T::Hash,
+    ) -> rstd::result::Result<(T::AccountId, T::Balance), &'static str> {
+        ensure!(kitty_id != sire_id, "A kitty can't breed with itself");
+
+        let owner = Self::owner_of(kitty_id).ok_or("This cat does not exist")?;
+        ensure!(owner == *sender, "You do not own this kitty");
+
+        let sire_owner = Self::owner_of(sire_id).ok_or("This sire does not exist")?;
+        ensure!(sire_owner != *sender, "You already own this sire");
+        let sire = Self::kitty(sire_id);
+        let fee = sire.sire_fee.ok_or("This kitty is not offered as a sire")?;
+        ensure!(sire.gender == Gender::Male, "Only male kitties can be offered as sires");
+
+        Self::ensure_breeding_pair(kitty_id, sire_id)?;
+
+        Ok((sire_owner, fee))
+    }
+
+    // 所有権以外の交配ルール(オークション、親子・兄弟、クールダウン)を確認する
+    fn ensure_breeding_pair(kitty_id_1: T::Hash, kitty_id_2: T::Hash) -> Result {
+        Self::ensure_not_in_auction(kitty_id_1)?;
+        Self::ensure_not_in_auction(kitty_id_2)?;
+
+        // 親子や兄弟の交配は認めない
+        ensure!(!Self::is_parent_of(kitty_id_1, kitty_id_2) && !Self::is_parent_of(kitty_id_2, kitty_id_1),
+            "A kitty can't breed with its own parent");
+        ensure!(!Self::are_siblings(kitty_id_1, kitty_id_2), "A kitty can't breed with its sibling");
+
+        // クールダウン中のKittyは交配できない
+        let now = <system::Module<T>>::block_number();
+        ensure!(Self::kitty(kitty_id_1).ready_at <= now, "This cat 1 is still cooling down");
+        ensure!(Self::kitty(kitty_id_2).ready_at <= now, "This cat 2 is still cooling down");
+
+        ensure!(Self::kitty(kitty_id_1).gender != Self::kitty(kitty_id_2).gender,
+            "Only a male and a female can breed");
+
+        Ok(())
+    }
+
+    fn is_parent_of(parent: T::Hash, child: T::Hash) -> bool {
+        Self::parents_of(child).map_or(false, |(p1, p2)| p1 == parent || p2 == parent)
+    }
+
+    // 片親でも共通していれば兄弟とみなす
+    fn are_siblings(kitty_id_1: T::Hash, kitty_id_2: T::Hash) -> bool {
+        match (Self::parents_of(kitty_id_1), Self::parents_of(kitty_id_2)) {
+            (Some((a1, a2)), Some((b1, b2))) => a1 == b1 || a1 == b2 || a2 == b1 || a2 == b2,
+            _ => false,
+        }
+    }
+
+    // 交配できるようになるまでの残りブロック数(UI向け)
+    pub fn cooldown_remaining(kitty_id: T::Hash) -> T::BlockNumber {
+        let ready_at = Self::kitty(kitty_id).ready_at;
+        let now = <system::Module<T>>::block_number();
+
+        if ready_at > now {
+            ready_at - now
+        } else {
+            Zero::zero()
+        }
+    }
+
+    // 世代に応じた交配クールダウンのブロック数をTraitのテーブルから求める
+    fn breeding_cooldown(gen: u64) -> T::BlockNumber {
+        let cooldowns = T::BREEDING_COOLDOWNS;
+        let blocks = match cooldowns.len() {
+            0 => 0,
+            len => cooldowns[cmp::min(gen, (len - 1) as u64) as usize],
+        };
+
+        <T::BlockNumber as As<u64>>::sa(blocks)
+    }
+
+    fn start_cooldown(kitty_id: T::Hash, now: T::BlockNumber) {
+        <Kitties<T>>::mutate(kitty_id, |kitty| {
+            kitty.ready_at = now + Self::breeding_cooldown(kitty.gen);
+        });
+    }
+
+    // オークション中のKittyはロックされ、移転や交配ができない
+    fn ensure_not_in_auction(kitty_id: T::Hash) -> Result {
+        ensure!(!<Auctions<T>>::exists(kitty_id), "This kitty is locked in an auction");
+
+        Ok(())
+    }
+
+    // 終了したオークションを精算し、最高入札者にKittyを渡す
+    // on_finalizeから呼ばれるため、精算できない場合は入札者に返金してオークションを取り消す
+    fn settle_auction(kitty_id: T::Hash) {
+        let auction = match <Auctions<T>>::take(kitty_id) {
+            Some(auction) => auction,
+            None => return,
+        };
+
+        let bidder = match auction.highest_bidder {
+            Some(bidder) => bidder,
+            None => {
+                Self::deposit_event(RawEvent::AuctionCancelled(auction.seller, kitty_id));
+                return;
+            }
+        };
+
+        // 資金を動かす前にKittyを渡せることを確認する
+        let seller_owns_kitty = Self::owner_of(kitty_id).as_ref() == Some(&auction.seller);
+        if !seller_owns_kitty || Self::ensure_can_receive(&bidder).is_err() {
+            <balances::Module<T> as ReservableCurrency<_>>::unreserve(&bidder, auction.highest_bid);
+            Self::deposit_event(RawEvent::AuctionCancelled(auction.seller, kitty_id));
+            return;
+        }
+
+        // 代金を受け取れた場合だけKittyを渡す
+        if Self::pay_sale(&bidder, &auction.seller, kitty_id, auction.highest_bid, true).is_err() {
+            <balances::Module<T> as ReservableCurrency<_>>::unreserve(&bidder, auction.highest_bid);
+            Self::deposit_event(RawEvent::AuctionCancelled(auction.seller, kitty_id));
+            return;
+        }
+
+        Self::transfer_from(auction.seller.clone(), bidder.clone(), kitty_id)
+            .expect("`seller` is shown to own the kitty; \
+            `bidder` is shown to be able to receive another kitty; \
+            qed");
+
+        Self::deposit_event(RawEvent::AuctionSettled(auction.seller, bidder, kitty_id, auction.highest_bid));
+    }
+
+    // 売却代金を売り手と作成者へ支払う(buy_kitty、accept_offer、settle_auctionで共通)
+    // reservedがtrueの場合はbuyerが確保していた資金から支払う
+    // 作成者がロイヤリティを受け取れない場合、ロイヤリティは売り手に支払う
+    fn pay_sale(
+        buyer: &T::AccountId,
+        seller: &T::AccountId,
+        kitty_id: T::Hash,
+        price: T::Balance,
+        reserved: bool,
+    ) -> Result {
+        // 作成者が売り手・買い手以外であれば、代金の一部をロイヤリティとして支払う
+        let royalty_to = Self::creator_of(kitty_id)
+            .filter(|creator| creator != seller && creator != buyer)
+            .map(|creator| (creator, Self::royalty_rate() * price))
+            .filter(|(creator, royalty)| !royalty.is_zero() && Self::can_be_paid(creator, *royalty));
+        let royalty = royalty_to.as_ref().map_or(Zero::zero(), |(_, royalty)| *royalty);
+
+        // 資金を動かす前に売り手が受け取れることを確認する
+        ensure!(Self::can_be_paid(seller, price - royalty), "The seller can't receive this payment");
+
+        // 売り手へ先に支払う
+        Self::pay(buyer, seller, price - royalty, reserved)?;
+
+        if let Some((creator, royalty)) = royalty_to {
+            Self::pay(buyer, &creator, royalty, reserved)?;
+            Self::deposit_event(RawEvent::RoyaltyPaid(creator, kitty_id, royalty));
+        }
+
+        Ok(())
+    }
+
+    // 残高が0のアカウントは、存在預金以上を受け取らないと作成されない
+    fn can_be_paid(who: &T::AccountId, amount: T::Balance) -> bool {
+        !<balances::Module<T> as Currency<_>>::total_balance(who).is_zero()
+            || amount >= <balances::Module<T>>::existential_deposit()
+    }
+
+    fn pay(from: &T::AccountId, to: &T::AccountId, amount: T::Balance, reserved: bool) -> Result {
+        if reserved {
+            Self::pay_reserved(from, to, amount)
+        } else {
+            <balances::Module<T> as Currency<_>>::transfer(from, to, amount)
+        }
+    }
+
+    // whoが確保していた資金をtoへ支払う
+    // toのアカウントが存在しない場合は確保を解除してから通常の送金でアカウントを作る
+    // この送金も失敗した場合、確保を解除した資金はwhoの手元に残る
+    fn pay_reserved(who: &T::AccountId, to: &T::AccountId, amount: T::Balance) -> Result {
+        if <balances::Module<T> as ReservableCurrency<_>>::repatriate_reserved(who, to, amount).is_err() {
+            <balances::Module<T> as ReservableCurrency<_>>::unreserve(who, amount);
+            <balances::Module<T> as Currency<_>>::transfer(who, to, amount)?;
+        }
+
+        Ok(())
+    }
+
+    // 受け取り側がKittyをもう一匹所有できるか確認する
+    fn ensure_can_receive(to: &T::AccountId) -> Result {
+        Self::ensure_can_receive_many(to, 1)
+    }
+
+    // 受け取り側がKittyをさらにcount匹所有できるか確認する
+    fn ensure_can_receive_many(to: &T::AccountId, count: u64) -> Result {
+        let new_owned_kitty_count = Self::owned_kitty_count(to).checked_add(count)
+            .ok_or("overflow adding a new kitty to owned kitty count")?;
+
+        if let Some(max_owned) = Self::max_owned_per_account() {
+            ensure!(new_owned_kitty_count <= max_owned, "The account would own more kitties than allowed per account");
+        }
+
+        Ok(())
+    }
+
+    // 総数の上限を超えずにcount匹のKittyをmintできるか確認する
+    fn ensure_can_mint(count: u64) -> Result {
+        let new_all_kitties_count = Self::all_kitties_count().checked_add(count)
+            .ok_or("overflow adding a new kitty to total supply")?;
+
+        if let Some(max_supply) = Self::max_supply() {
+            ensure!(new_all_kitties_count <= max_supply, "The maximum supply of kitties has been reached");
+        }
+
+        Ok(())
+    }
+
+    // オファーをストレージから取り除く(確保された資金の扱いは呼び出し側で行う)
+    fn remove_offer(kitty_id: T::Hash, bidder: &T::AccountId) {
+        <Offers<T>>::remove(&kitty_id, bidder);
+        <OfferBidders<T>>::mutate(kitty_id, |bidders| bidders.retain(|b| b != bidder));
+    }
+
+    // Kittyへのオファーを全て取り除き、確保されていた資金を返却する
+    fn clear_offers(kitty_id: T::Hash) {
+        for bidder in <OfferBidders<T>>::take(kitty_id) {
+            if let Some(offer) = Self::offer_of(&kitty_id, &bidder) {
+                <Offers<T>>::remove(&kitty_id, &bidder);
+                <balances::Module<T> as ReservableCurrency<_>>::unreserve(&bidder, offer.amount);
+            }
+        }
+    }
+
+    fn burn(owner: T::AccountId, kitty_id: T::Hash) -> Result {
+        // 事前チェック
+        let all_kitties_count = Self::all_kitties_count();
+        let new_all_kitties_count = all_kitties_count.checked_sub(1)
+            .ok_or("underflow removing a kitty from total supply")?;
+
+        // 所有者のリストからswap and popで取り除く
+        Self::remove_owned_kitty(&owner, kitty_id)?;
+        <OwnedKittiesIndex<T>>::remove(kitty_id);
+
+        // AllKittiesArrayからも同じくswap and popで取り除く
+        let kitty_index = <AllKittiesIndex<T>>::get(kitty_id);
+        if kitty_index != new_all_kitties_count {
+            let last_kitty_id = <AllKittiesArray<T>>::get(new_all_kitties_count);
+            <AllKittiesArray<T>>::insert(kitty_index, last_kitty_id);
+            <AllKittiesIndex<T>>::insert(last_kitty_id, kitty_index);
+        }
+        <AllKittiesArray<T>>::remove(new_all_kitties_count);
+        <AllKittiesIndex<T>>::remove(kitty_id);
+        <AllKittiesCount<T>>::put(new_all_kitties_count);
+
+        // Kitty本体と所有者の情報を削除
+        <Kitties<T>>::remove(kitty_id);
+        <KittyOwner<T>>::remove(kitty_id);
+        <KittyCreator<T>>::remove(kitty_id);
+
+        // 販売情報とオファー、交配許可を片付ける
+        Self::remove_from_sale_index(kitty_id);
+        <ListingExpiry<T>>::remove(kitty_id);
+        <DutchAuctions<T>>::remove(kitty_id);
+        Self::clear_offers(kitty_id);
+        <KittyApprovals<T>>::remove(kitty_id);
+        <SireAllowedTo<T>>::remove(kitty_id);
+
+        // 名前とメタデータを消してデポジットを返却する
+        if let Some(name) = <KittyNames<T>>::take(kitty_id) {
+            <KittyByName<T>>::remove(name);
+        }
+        <KittyMetadataOf<T>>::remove(kitty_id);
+        if let Some((depositor, deposit)) = <KittyDeposits<T>>::take(kitty_id) {
+            <balances::Module<T> as ReservableCurrency<_>>::unreserve(&depositor, deposit);
+        }
+
+        Self::deposit_event(RawEvent::Burned(owner, kitty_id));
+
+        Ok(())
+    }
+
+    // OwnedKittiesArrayからswap and popでKittyを取り除き、所有数を一つ減らす
+    // OwnedKittiesIndexの後始末は呼び出し側で行う
+    fn remove_owned_kitty(owner: &T::AccountId, kitty_id: T::Hash) -> Result {
+        let owned_kitty_count = Self::owned_kitty_count(owner);
+        let new_owned_kitty_count = owned_kitty_count.checked_sub(1)
+            .ok_or("underflow removing a kitty from owned kitty count")?;
+
+        // 最後のKittyを空いた位置に移動させる
+        let kitty_index = <OwnedKittiesIndex<T>>::get(kitty_id);
+        if kitty_index != new_owned_kitty_count {
+            let last_kitty_id = <OwnedKittiesArray<T>>::get((owner.clone(), new_owned_kitty_count));
+            <OwnedKittiesArray<T>>::insert((owner.clone(), kitty_index), last_kitty_id);
+            <OwnedKittiesIndex<T>>::insert(last_kitty_id, kitty_index);
+        }
+
+        <OwnedKittiesArray<T>>::remove((owner.clone(), new_owned_kitty_count));
+        <OwnedKittiesCount<T>>::insert(owner, new_owned_kitty_count);
+
+        Ok(())
+    }
+
+    // 名前とメタデータの合計バイト数に応じたデポジットをwhoから確保し直す
+    // 以前に別のアカウントが確保していたデポジットはそのアカウントに返却する
+    fn update_deposit(who: &T::AccountId, kitty_id: T::Hash, bytes: usize) -> Result {
+        let deposit = <T::Balance as As<u64>>::sa(T::DEPOSIT_PER_BYTE.saturating_mul(bytes as u64));
+
+        match Self::deposit_of(kitty_id) {
+            Some((ref depositor, old_deposit)) if depositor == who => {
+                if deposit > old_deposit {
+                    <balances::Module<T> as ReservableCurrency<_>>::reserve(who, deposit - old_deposit)?;
+                } else {
+                    <balances::Module<T> as ReservableCurrency<_>>::unreserve(who, old_deposit - deposit);
+                }
+            }
+            previous => {
+                // 失敗した場合に何も変わらないよう、先に新しいデポジットを確保する
+                <balances::Module<T> as ReservableCurrency<_>>::reserve(who, deposit)?;
+                if let Some((depositor, old_deposit)) = previous {
+                    <balances::Module<T> as ReservableCurrency<_>>::unreserve(&depositor, old_deposit);
+                }
+            }
+        }
+
+        if bytes == 0 {
+            <KittyDeposits<T>>::remove(kitty_id);
+        } else {
+            <KittyDeposits<T>>::insert(kitty_id, (who.clone(), deposit));
+        }
+
+        Ok(())
+    }
+
+    // 最初のレイアウトのKittyを読み直し、追加されたフィールドを埋める
+    // クールダウンは終わっていて種親としては貸し出されておらず、性別はDNAから決まる
+    fn migrate_kitties() {
+        for index in 0..Self::all_kitties_count() {
+            let kitty_id = Self::kitty_by_index(index);
+            let key = <Kitties<T>>::key_for(kitty_id);
+
+            if let Some(old) = support::storage::get::<KittyV0<T::Hash, T::Balance>>(&key) {
+                let kitty = Kitty {
+                    id: old.id,
+                    dna: old.dna,
+                    price: old.price,
+                    gen: old.gen,
+                    ready_at: Zero::zero(),
+                    sire_fee: None,
+                    gender: genetics::gender(old.dna.as_ref()),
+                };
+                <Kitties<T>>::insert(kitty_id, kitty);
+            }
+        }
+    }
+
+    // インデックスが追加される前から売りに出されているKittyを登録する
+    fn index_kitties_for_sale() {
+        for index in 0..Self::all_kitties_count() {
+            let kitty = Self::kitty(Self::kitty_by_index(index));
+            if !kitty.price.is_zero() && Self::for_sale_position(kitty.id).is_none() {
+                Self::add_to_sale_index(kitty.id, kitty.price);
+            }
+        }
+    }
+}
+
+// ストレージの整合性チェック(テストとノードのCLIから使う)
+#[cfg(feature = "std")]
+impl<T: Trait> Module<T> {
+    // 所有関係を表す6つのマップを突き合わせ、見つかった不整合を全て報告する
+    // マップは列挙できないため、アカウントはKittyの所有者から辿れるものだけを調べる
+    pub fn check_integrity() -> rstd::result::Result<(), Vec<String>> {
+        use std::collections::{BTreeMap, BTreeSet};
+
+        let mut errors = Vec::new();
+        let mut seen = BTreeSet::new();
+        let mut owned_by: BTreeMap<T::AccountId, Vec<T::Hash>> = BTreeMap::new();
+
+        let all_kitties_count = Self::all_kitties_count();
+        for index in 0..all_kitties_count {
+            let kitty_id = Self::kitty_by_index(index);
+
+            if !seen.insert(kitty_id) {
+                errors.push(format!("AllKittiesArray[{}]: kitty {:?} is listed more than once", index, kitty_id));
+                continue;
+            }
+            if !<Kitties<T>>::exists(kitty_id) {
+                errors.push(format!("AllKittiesArray[{}]: dangling kitty id {:?}", index, kitty_id));
+            }
+            let all_index = <AllKittiesIndex<T>>::get(kitty_id);
+            if all_index != index {
+                errors.push(format!("AllKittiesIndex[{:?}] is {} but the kitty is at {}", kitty_id, all_index, index));
+            }
+            match Self::owner_of(kitty_id) {
+                Some(owner) => owned_by.entry(owner).or_insert_with(Vec::new).push(kitty_id),
+                None => errors.push(format!("KittyOwner[{:?}] is missing", kitty_id)),
+            }
+        }
+        if <AllKittiesArray<T>>::exists(all_kitties_count) {
+            errors.push(format!("AllKittiesArray[{}] is set past AllKittiesCount", all_kitties_count));
+        }
+
+        for (owner, kitty_ids) in owned_by {
+            let count = Self::owned_kitty_count(&owner);
+            if count != kitty_ids.len() as u64 {
+                errors.push(format!(
+                    "OwnedKittiesCount[{:?}] is {} but KittyOwner assigns {} kitties to it",
+                    owner, count, kitty_ids.len()
+                ));
+            }
+
+            for index in 0..count {
+                let kitty_id = Self::kitty_of_owner_by_index((owner.clone(), index));
+                if Self::owner_of(kitty_id).as_ref() != Some(&owner) {
+                    errors.push(format!(
+                        "OwnedKittiesArray[({:?}, {})] holds {:?} which KittyOwner does not assign to it",
+                        owner, index, kitty_id
+                    ));
+                }
+            }
+            if <OwnedKittiesArray<T>>::exists((owner.clone(), count)) {
+                errors.push(format!("OwnedKittiesArray[({:?}, {})] is set past OwnedKittiesCount", owner, count));
+            }
+
+            for kitty_id in kitty_ids {
+                let index = <OwnedKittiesIndex<T>>::get(kitty_id);
+                if index >= count || Self::kitty_of_owner_by_index((owner.clone(), index)) != kitty_id {
+                    errors.push(format!(
+                        "OwnedKittiesIndex[{:?}] is {} but OwnedKittiesArray[({:?}, {})] does not hold it",
+                        kitty_id, index, owner, index
+                    ));
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// tests for this module
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use runtime_io::with_externalities;
+    use primitives::{H256, Blake2Hasher};
+    use support::{impl_outer_origin, impl_outer_event, assert_ok, assert_noop};
+    use runtime_primitives::{
+        BuildStorage,
+        traits::{BlakeTwo256, IdentityLookup, OnFinalize, OnInitialize},
+        testing::{Digest, DigestItem, Header}
+    };
+
+    impl_outer_origin! {
+        pub enum Origin for Test {}
+    }
+
+    mod kitties {
+        pub use super::super::*;
+    }
+
+    impl_outer_event! {
+        pub enum TestEvent for Test {
+            balances<T>,
+            kitties<T>,
+        }
+    }
+
+    // system、balancesとこのモジュールを組み合わせたモックランタイム
+    #[derive(Clone, Eq, PartialEq)]
+    pub struct Test;
+    impl system::Trait for Test {
+        type Origin = Origin;
+        type Index = u64;
+        type BlockNumber = u64;
+        type Hash = H256;
+        type Hashing = BlakeTwo256;
+        type Digest = Digest;
+        type AccountId = u64;
+        type Lookup = IdentityLookup<Self::AccountId>;
+        type Header = Header;
+        type Event = TestEvent;
+        type Log = DigestItem;
+    }
+    impl balances::Trait for Test {
+        type Balance = u64;
+        type OnFreeBalanceZero = ();
+        type OnNewAccount = ();
+        type Event = TestEvent;
+        type TransactionPayment = ();
+        type TransferPayment = ();
+        type DustRemoval = ();
+    }
+    impl Trait for Test {
+        type Event = TestEvent;
+        type BlockAuthor = ();
+        const BREEDING_COOLDOWNS: &'static [u64] = &[1, 2, 5];
+        const MUTATION_RATES: &'static [u32] = &[];
+        const REVEAL_DELAY: u64 = 2;
+        const REVEAL_PERIOD: u64 = 5;
+        const MIN_NAME_LENGTH: usize = 3;
+        const MAX_NAME_LENGTH: usize = 8;
+        const MAX_METADATA_URI_LENGTH: usize = 16;
+        const DEPOSIT_PER_BYTE: u64 = 10;
+        const MAX_BATCH_SIZE: usize = 3;
+        const MAX_LISTING_EXPIRIES_PER_BLOCK: usize = 2;
+        const MAX_OFFERS_PER_KITTY: usize = 2;
+        const MIN_OFFER_AMOUNT: u64 = 10;
+    }
+    type KittyModule = Module<Test>;
+
+    // 残高を持つアカウントと、あらかじめmintしておくKittyを指定してテスト環境を作る
+    struct ExtBuilder {
+        balances: Vec<(u64, u64)>,
+        existential_deposit: u64,
+        kitties: Vec<(u64, u64)>,
+    }
+
+    impl Default for ExtBuilder {
+        // アカウント1〜3に残高を持たせ、Kittyはまだいない
+        fn default() -> Self {
+            ExtBuilder {
+                balances: vec![(1, 1_000), (2, 1_000), (3, 1_000)],
+                existential_deposit: 0,
+                kitties: vec![],
+            }
+        }
+    }
+
+    impl ExtBuilder {
+        fn balances(mut self, balances: Vec<(u64, u64)>) -> Self {
+            self.balances = balances;
+            self
+        }
+
+        fn existential_deposit(mut self, existential_deposit: u64) -> Self {
+            self.existential_deposit = existential_deposit;
+            self
+        }
+
+        // ownerごとにcount匹の第0世代のKittyを作っておく
+        fn kitties(mut self, kitties: Vec<(u64, u64)>) -> Self {
+            self.kitties = kitties;
+            self
+        }
+
+        fn build(self) -> runtime_io::TestExternalities<Blake2Hasher> {
+            let mut t = system::GenesisConfig::<Test>::default().build_storage().unwrap().0;
+            t.extend(balances::GenesisConfig::<Test> {
+                balances: self.balances,
+                transaction_base_fee: 0,
+                transaction_byte_fee: 0,
+                existential_deposit: self.existential_deposit,
+                transfer_fee: 0,
+                creation_fee: 0,
+                vesting: vec![],
+            }.build_storage().unwrap().0);
+
+            let mut ext: runtime_io::TestExternalities<Blake2Hasher> = t.into();
+            let kitties = self.kitties;
+            with_externalities(&mut ext, || {
+                for (owner, count) in kitties {
+                    for _ in 0..count {
+                        KittyModule::mint_batch(Origin::ROOT, owner, 1).expect("the mock sets no supply or holding limit; qed");
+                    }
+                }
+            });
+            ext
+        }
+    }
+
+    fn new_test_ext() -> runtime_io::TestExternalities<Blake2Hasher> {
+        ExtBuilder::default().build()
+    }
+
+    // 最後に発行されたイベント
+    fn last_event() -> TestEvent {
+        system::Module::<Test>::events().pop().expect("an event was deposited").event
+    }
+
+    // このモジュールが発行したイベントを古い順に返す
+    fn kitty_events() -> Vec<RawEvent<u64, H256, u64, u64>> {
+        system::Module::<Test>::events().into_iter()
+            .filter_map(|record| match record.event {
+                TestEvent::kitties(event) => Some(event),
+                _ => None,
+            })
+            .collect()
+    }
+
+    // 所有関係のマップが互いに矛盾していないことを確かめる
+    fn assert_integrity() {
+        assert_eq!(KittyModule::check_integrity(), Ok(()));
+    }
+
+    // 自由残高と確保された残高をまとめて確かめる
+    fn assert_balances(who: u64, free: u64, reserved: u64) {
+        assert_eq!(<balances::Module<Test>>::free_balance(&who), free);
+        assert_eq!(<balances::Module<Test>>::reserved_balance(&who), reserved);
+    }
+
+    // rootにownerのKittyを1匹作成させ、そのIDを返す(ブロックは進めない)
+    fn create_kitty(owner: u64) -> H256 {
+        assert_ok!(KittyModule::mint_batch(Origin::ROOT, owner, 1));
+        assert_integrity();
+        let count = KittyModule::owned_kitty_count(owner);
+        KittyModule::kitty_of_owner_by_index((owner, count - 1))
+    }
+
+    // ownerにKittyの作成をコミットしてからrevealさせ、そのIDを返す
+    fn commit_and_create(owner: u64) -> H256 {
+        assert_ok!(KittyModule::commit_create(Origin::signed(owner), commitment(owner)));
+        wait_for_reveal();
+        assert_ok!(KittyModule::reveal_create(Origin::signed(owner), secret(owner)));
+        assert_integrity();
+        let count = KittyModule::owned_kitty_count(owner);
+        KittyModule::kitty_of_owner_by_index((owner, count - 1))
+    }
+
+    // 性別を指定してKittyを作成する
+    fn create_kitty_with_gender(owner: u64, gender: Gender) -> H256 {
+        let kitty_id = create_kitty(owner);
+        <Kitties<Test>>::mutate(kitty_id, |kitty| kitty.gender = gender);
+        kitty_id
+    }
+
+    fn create_female(owner: u64) -> H256 {
+        create_kitty_with_gender(owner, Gender::Female)
+    }
+
+    fn create_male(owner: u64) -> H256 {
+        create_kitty_with_gender(owner, Gender::Male)
+    }
+
+    // 交配をコミットしたownerが使うsecret
+    fn secret(owner: u64) -> H256 {
+        H256::from_low_u64_be(owner)
+    }
+
+    fn commitment(owner: u64) -> H256 {
+        BlakeTwo256::hash_of(&secret(owner))
+    }
+
+    // 今コミットしたコミットメントをrevealできる、reveal_atの次のブロックまで進める
+    fn wait_for_reveal() {
+        let now = system::Module::<Test>::block_number();
+        system::Module::<Test>::set_block_number(now + Test::REVEAL_DELAY + 1);
+    }
+
+    // ownerに交配をコミットしてからrevealさせ、生まれたKittyのIDを返す
+    fn breed_kitty(owner: u64, kitty_id_1: H256, kitty_id_2: H256) -> H256 {
+        assert_ok!(KittyModule::commit_breed(Origin::signed(owner), kitty_id_1, kitty_id_2, commitment(owner)));
+        wait_for_reveal();
+        assert_ok!(KittyModule::reveal_breed(Origin::signed(owner), secret(owner)));
+        assert_integrity();
+        let count = KittyModule::owned_kitty_count(owner);
+        KittyModule::kitty_of_owner_by_index((owner, count - 1))
+    }
+
+    #[test]
+    fn breed_kitty_works_with_own_kitties() {
+        with_externalities(&mut new_test_ext(), || {
+            let kitty_1 = create_female(1);
+            let kitty_2 = create_male(1);
+
+            let child = breed_kitty(1, kitty_1, kitty_2);
+
+            assert_eq!(KittyModule::owner_of(child), Some(1));
+            assert_eq!(KittyModule::parents_of(child), Some((kitty_1, kitty_2)));
+            assert_eq!(KittyModule::children_of(kitty_1), vec![child]);
+            assert_eq!(KittyModule::children_of(kitty_2), vec![child]);
+            assert_eq!(KittyModule::kitty(child).gen, 1);
+        });
+    }
+
+    #[test]
+    fn breed_kitty_rejects_the_same_kitty_twice() {
+        with_externalities(&mut new_test_ext(), || {
+            let kitty = create_female(1);
+
+            assert_noop!(
+                KittyModule::commit_breed(Origin::signed(1), kitty, kitty, commitment(1)),
+                "A kitty can't breed with itself"
+            );
+        });
+    }
+
+    #[test]
+    fn breed_kitty_requires_ownership() {
+        with_externalities(&mut new_test_ext(), || {
+            let kitty_1 = create_female(1);
+            let kitty_2 = create_male(2);
+
+            assert_noop!(
+                KittyModule::commit_breed(Origin::signed(3), kitty_1, kitty_2, commitment(3)),
+                "You do not own either of these cats"
+            );
+            assert_noop!(
+                KittyModule::commit_breed(Origin::signed(1), kitty_1, kitty_2, commitment(1)),
+                "You do not own cat 2 and have no permission to breed with it"
+            );
+            assert_noop!(
+                KittyModule::commit_breed(Origin::signed(2), kitty_1, kitty_2, commitment(2)),
+                "You do not own cat 1 and have no permission to breed with it"
+            );
+        });
+    }
+
+    #[test]
+    fn sire_permission_allows_breeding_once() {
+        with_externalities(&mut new_test_ext(), || {
+            let kitty_1 = create_female(1);
+            let kitty_2 = create_male(2);
+
+            assert_noop!(
+                KittyModule::approve_siring(Origin::signed(1), kitty_2, 1),
+                "You do not own this kitty"
+            );
+            assert_ok!(KittyModule::approve_siring(Origin::signed(2), kitty_2, 1));
+
+            let child = breed_kitty(1, kitty_1, kitty_2);
+            assert_eq!(KittyModule::owner_of(child), Some(1));
+            assert_eq!(KittyModule::sire_allowed_to(kitty_2), None);
+
+            system::Module::<Test>::set_block_number(10);
+            assert_noop!(
+                KittyModule::commit_breed(Origin::signed(1), kitty_1, kitty_2, commitment(1)),
+                "You do not own cat 2 and have no permission to breed with it"
+            );
+        });
+    }
+
+    #[test]
+    fn sire_permission_is_cleared_on_transfer() {
+        with_externalities(&mut new_test_ext(), || {
+            let kitty_1 = create_female(1);
+            let kitty_2 = create_male(2);
+
+            assert_ok!(KittyModule::approve_siring(Origin::signed(2), kitty_2, 1));
+            assert_ok!(KittyModule::transfer(Origin::signed(2), 3, kitty_2));
+
+            assert_eq!(KittyModule::sire_allowed_to(kitty_2), None);
+            assert_noop!(
+                KittyModule::commit_breed(Origin::signed(1), kitty_1, kitty_2, commitment(1)),
+                "You do not own cat 2 and have no permission to breed with it"
+            );
+        });
+    }
+
+    #[test]
+    fn breed_with_sire_pays_the_fee_on_reveal() {
+        with_externalities(&mut new_test_ext(), || {
+            let kitty = create_female(1);
+            let sire = create_male(2);
+
+            assert_noop!(
+                KittyModule::commit_breed_with_sire(Origin::signed(1), kitty, sire, commitment(1)),
+                "This kitty is not offered as a sire"
+            );
+            assert_ok!(KittyModule::offer_sire(Origin::signed(2), sire, 100));
+            assert_ok!(KittyModule::commit_breed_with_sire(Origin::signed(1), kitty, sire, commitment(1)));
+            assert_eq!(balances::Module::<Test>::free_balance(&1), 900);
+            assert_eq!(balances::Module::<Test>::reserved_balance(&1), 100);
+
+            wait_for_reveal();
+            assert_ok!(KittyModule::reveal_breed(Origin::signed(1), secret(1)));
+
+            assert_eq!(KittyModule::owned_kitty_count(1), 2);
+            assert_eq!(KittyModule::sire_uses(sire), 1);
+            assert_eq!(balances::Module::<Test>::reserved_balance(&1), 0);
+            assert_eq!(balances::Module::<Test>::free_balance(&2), 1_100);
+
+            assert_ok!(KittyModule::delist_sire(Origin::signed(2), sire));
+            system::Module::<Test>::set_block_number(10);
+            assert_noop!(
+                KittyModule::commit_breed_with_sire(Origin::signed(1), kitty, sire, commitment(1)),
+                "This kitty is not offered as a sire"
+            );
+        });
+    }
+
+    #[test]
+    fn only_males_can_be_rented_as_sires() {
+        with_externalities(&mut new_test_ext(), || {
+            let kitty = create_female(1);
+            let female = create_female(2);
+            let sire = create_male(2);
+
+            assert_noop!(
+                KittyModule::offer_sire(Origin::signed(2), female, 100),
+                "Only male kitties can be offered as sires"
+            );
+
+            // メスの所有者が受け取る設定でも、生まれたKittyは借りた側のものになる
+            assert_ok!(KittyModule::set_offspring_to_female_owner(Origin::ROOT, true));
+            assert_ok!(KittyModule::offer_sire(Origin::signed(2), sire, 100));
+            assert_ok!(KittyModule::commit_breed_with_sire(Origin::signed(1), kitty, sire, commitment(1)));
+            wait_for_reveal();
+            assert_ok!(KittyModule::reveal_breed(Origin::signed(1), secret(1)));
+
+            assert_eq!(KittyModule::owned_kitty_count(1), 2);
+            assert_eq!(KittyModule::owned_kitty_count(2), 2);
+            assert_integrity();
+        });
+    }
+
+    #[test]
+    fn reveal_breed_waits_for_the_delay() {
+        with_externalities(&mut new_test_ext(), || {
+            let kitty_1 = create_female(1);
+            let kitty_2 = create_male(1);
+
+            assert_ok!(KittyModule::commit_breed(Origin::signed(1), kitty_1, kitty_2, commitment(1)));
+            assert_noop!(
+                KittyModule::commit_breed(Origin::signed(1), kitty_1, kitty_2, commitment(1)),
+                "This commitment is already in use"
+            );
+            assert_noop!(
+                KittyModule::reveal_breed(Origin::signed(1), secret(1)),
+                "This breeding commitment can't be revealed yet"
+            );
+
+            // ハッシュをDNAに使うブロックの中ではまだrevealできない
+            system::Module::<Test>::set_block_number(Test::REVEAL_DELAY);
+            assert_noop!(
+                KittyModule::reveal_breed(Origin::signed(1), secret(1)),
+                "This breeding commitment can't be revealed yet"
+            );
+
+            system::Module::<Test>::set_block_number(Test::REVEAL_DELAY + 1);
+            assert_noop!(
+                KittyModule::reveal_breed(Origin::signed(1), secret(2)),
+                "There is no breeding commitment for this secret"
+            );
+            assert_noop!(
+                KittyModule::reveal_breed(Origin::signed(2), secret(1)),
+                "There is no breeding commitment for this secret"
+            );
+            assert_ok!(KittyModule::reveal_breed(Origin::signed(1), secret(1)));
+
+            assert_eq!(KittyModule::owned_kitty_count(1), 3);
+            assert_eq!(KittyModule::breed_commitment(&1, &commitment(1)), None);
+        });
+    }
+
+    #[test]
+    fn commitments_of_other_accounts_do_not_collide() {
+        with_externalities(&mut new_test_ext(), || {
+            let kitty_1 = create_female(1);
+            let kitty_2 = create_male(1);
+            let kitty_3 = create_female(2);
+            let kitty_4 = create_male(2);
+
+            // 公開されたコミットメントを他のアカウントが先に使っても邪魔できない
+            assert_ok!(KittyModule::commit_breed(Origin::signed(2), kitty_3, kitty_4, commitment(1)));
+            assert_ok!(KittyModule::commit_breed(Origin::signed(1), kitty_1, kitty_2, commitment(1)));
+            assert_ok!(KittyModule::commit_create(Origin::signed(2), commitment(1)));
+            assert_ok!(KittyModule::commit_create(Origin::signed(1), commitment(1)));
+
+            wait_for_reveal();
+            assert_ok!(KittyModule::reveal_breed(Origin::signed(1), secret(1)));
+            assert_ok!(KittyModule::reveal_create(Origin::signed(1), secret(1)));
+
+            assert_eq!(KittyModule::owned_kitty_count(1), 4);
+            assert!(KittyModule::breed_commitment(&2, &commitment(1)).is_some());
+            assert!(KittyModule::create_commitment(&2, &commitment(1)).is_some());
+            assert_integrity();
+        });
+    }
+
+    #[test]
+    fn offspring_does_not_depend_on_the_reveal_block() {
+        // 同じコミットメントを異なるブロックでrevealしたときに生まれるKittyのDNA
+        fn dna_revealed_at(block: u64) -> H256 {
+            with_externalities(&mut new_test_ext(), || {
+                let kitty_1 = create_female(1);
+                let kitty_2 = create_male(1);
+
+                assert_ok!(KittyModule::commit_breed(Origin::signed(1), kitty_1, kitty_2, commitment(1)));
+                system::Module::<Test>::set_block_number(block);
+                assert_ok!(KittyModule::reveal_breed(Origin::signed(1), secret(1)));
+
+                KittyModule::kitty(KittyModule::kitty_of_owner_by_index((1, 2))).dna
+            })
+        }
+
+        let first = dna_revealed_at(Test::REVEAL_DELAY + 1);
+        for block in Test::REVEAL_DELAY + 2..=Test::REVEAL_DELAY + Test::REVEAL_PERIOD {
+            assert_eq!(dna_revealed_at(block), first);
+        }
+    }
+
+    #[test]
+    fn unrevealed_commitment_expires_and_returns_the_fee() {
+        with_externalities(&mut new_test_ext(), || {
+            let kitty = create_female(1);
+            let sire = create_male(2);
+
+            assert_ok!(KittyModule::offer_sire(Origin::signed(2), sire, 100));
+            assert_ok!(KittyModule::commit_breed_with_sire(Origin::signed(1), kitty, sire, commitment(1)));
+
+            let expires_at = Test::REVEAL_DELAY + Test::REVEAL_PERIOD;
+            KittyModule::on_finalize(expires_at);
+
+            assert_eq!(KittyModule::breed_commitment(&1, &commitment(1)), None);
+            assert_eq!(balances::Module::<Test>::free_balance(&1), 1_000);
+            assert_eq!(balances::Module::<Test>::reserved_balance(&1), 0);
+
+            system::Module::<Test>::set_block_number(expires_at);
+            assert_noop!(
+                KittyModule::reveal_breed(Origin::signed(1), secret(1)),
+                "There is no breeding commitment for this secret"
+            );
+        });
+    }
+
+    #[test]
+    fn create_commitment_holds_the_mint_fee_until_reveal() {
+        with_externalities(&mut new_test_ext(), || {
+            assert_ok!(KittyModule::set_mint_fee(Origin::ROOT, 10));
+
+            assert_ok!(KittyModule::commit_create(Origin::signed(1), commitment(1)));
+            assert_noop!(
+                KittyModule::commit_create(Origin::signed(1), commitment(1)),
+                "This commitment is already in use"
+            );
+            assert_eq!(balances::Module::<Test>::reserved_balance(&1), 10);
+            assert_noop!(
+                KittyModule::reveal_create(Origin::signed(1), secret(1)),
+                "This creation commitment can't be revealed yet"
+            );
+
+            // revealされなかった作成のコミットメントは失効して手数料が返却される
+            let expires_at = Test::REVEAL_DELAY + Test::REVEAL_PERIOD;
+            KittyModule::on_finalize(expires_at);
+
+            assert_eq!(KittyModule::create_commitment(&1, &commitment(1)), None);
+            assert_eq!(balances::Module::<Test>::free_balance(&1), 1_000);
+            assert_eq!(balances::Module::<Test>::reserved_balance(&1), 0);
+            assert_eq!(last_event(), TestEvent::kitties(RawEvent::CreateCommitmentExpired(1, commitment(1))));
+
+            system::Module::<Test>::set_block_number(expires_at);
+            assert_noop!(
+                KittyModule::reveal_create(Origin::signed(1), secret(1)),
+                "There is no creation commitment for this secret"
+            );
+            assert_eq!(KittyModule::all_kitties_count(), 0);
+        });
+    }
+
+    #[test]
+    fn breed_kitty_rejects_parent_and_child() {
+        with_externalities(&mut new_test_ext(), || {
+            let kitty_1 = create_female(1);
+            let kitty_2 = create_male(1);
+            let child = breed_kitty(1, kitty_1, kitty_2);
+
+            system::Module::<Test>::set_block_number(10);
+            assert_noop!(
+                KittyModule::commit_breed(Origin::signed(1), child, kitty_1, commitment(1)),
+                "A kitty can't breed with its own parent"
+            );
+            assert_noop!(
+                KittyModule::commit_breed(Origin::signed(1), kitty_2, child, commitment(1)),
+                "A kitty can't breed with its own parent"
+            );
+        });
+    }
+
+    #[test]
+    fn breed_kitty_rejects_siblings() {
+        with_externalities(&mut new_test_ext(), || {
+            let kitty_1 = create_female(1);
+            let kitty_2 = create_male(1);
+            let kitty_3 = create_male(1);
+            let child_1 = breed_kitty(1, kitty_1, kitty_2);
+
+            system::Module::<Test>::set_block_number(10);
+            let child_2 = breed_kitty(1, kitty_1, kitty_3);
+
+            assert_noop!(
+                KittyModule::commit_breed(Origin::signed(1), child_1, child_2, commitment(1)),
+                "A kitty can't breed with its sibling"
+            );
+        });
+    }
+
+    #[test]
+    fn breed_kitty_respects_cooldown() {
+        with_externalities(&mut new_test_ext(), || {
+            let kitty_1 = create_female(1);
+            let kitty_2 = create_male(1);
+            let kitty_3 = create_male(1);
+            breed_kitty(1, kitty_1, kitty_2);
+
+            assert_eq!(KittyModule::cooldown_remaining(kitty_1), 1);
+            assert_noop!(
+                KittyModule::commit_breed(Origin::signed(1), kitty_1, kitty_3, commitment(1)),
+                "This cat 1 is still cooling down"
+            );
+
+            system::Module::<Test>::set_block_number(3);
+            assert_eq!(KittyModule::cooldown_remaining(kitty_1), 0);
+            assert_ok!(KittyModule::commit_breed(Origin::signed(1), kitty_1, kitty_3, commitment(1)));
+        });
+    }
+
+    #[test]
+    fn breed_kitty_requires_a_male_and_a_female() {
+        with_externalities(&mut new_test_ext(), || {
+            let kitty_1 = create_female(1);
+            let kitty_2 = create_female(1);
+
+            assert_noop!(
+                KittyModule::commit_breed(Origin::signed(1), kitty_1, kitty_2, commitment(1)),
+                "Only a male and a female can breed"
+            );
+        });
+    }
+
+    #[test]
+    fn offspring_can_go_to_the_female_owner() {
+        with_externalities(&mut new_test_ext(), || {
+            let male = create_male(1);
+            let female = create_female(2);
+            assert_ok!(KittyModule::approve_siring(Origin::signed(2), female, 1));
+
+            assert_noop!(
+                KittyModule::set_offspring_to_female_owner(Origin::signed(1), true),
+                "bad origin: expected to be a root origin"
+            );
+            assert_ok!(KittyModule::set_offspring_to_female_owner(Origin::ROOT, true));
+
+            assert_ok!(KittyModule::commit_breed(Origin::signed(1), male, female, commitment(1)));
+            wait_for_reveal();
+            assert_ok!(KittyModule::reveal_breed(Origin::signed(1), secret(1)));
+
+            assert_eq!(KittyModule::owned_kitty_count(1), 1);
+            assert_eq!(KittyModule::owned_kitty_count(2), 2);
+            let child = KittyModule::kitty_of_owner_by_index((2, 1));
+            assert_eq!(KittyModule::parents_of(child), Some((male, female)));
+        });
+    }
+
+    #[test]
+    fn migration_upgrades_kitties_from_the_baseline_layout() {
+        with_externalities(&mut new_test_ext(), || {
+            let mut dna = [0u8; 32];
+            dna[genetics::GENDER_BYTE] = 1;
+            let kitty_id = H256::from(dna);
+            // 最初のレイアウトのエンコードをそのまま書き込む
+            let old = (kitty_id, kitty_id, 50u64, 3u64);
+            support::storage::put(&<Kitties<Test>>::key_for(kitty_id), &old);
+            <AllKittiesArray<Test>>::insert(0, kitty_id);
+            <AllKittiesCount<Test>>::put(1);
+
+            KittyModule::on_initialize(1);
+
+            let kitty = KittyModule::kitty(kitty_id);
+            assert_eq!(kitty.gender, Gender::Female);
+            assert_eq!((kitty.id, kitty.dna, kitty.price, kitty.gen), (kitty_id, kitty_id, 50, 3));
+            assert_eq!((kitty.ready_at, kitty.sire_fee), (0, None));
+            assert_eq!(KittyModule::storage_version(), STORAGE_VERSION);
+            assert_eq!(KittyModule::for_sale_count(), 1);
+            assert_eq!(KittyModule::kitties_for_sale(0, 10), vec![kitty]);
+        });
+    }
+
+    #[test]
+    fn set_name_reserves_a_deposit_per_byte() {
+        with_externalities(&mut new_test_ext(), || {
+            let kitty = create_kitty(1);
+
+            assert_ok!(KittyModule::set_name(Origin::signed(1), kitty, b"Tom".to_vec()));
+            assert_eq!(KittyModule::kitty_by_name(b"Tom".to_vec()), Some(kitty));
+            assert_eq!(balances::Module::<Test>::reserved_balance(&1), 30);
+
+            assert_ok!(KittyModule::set_name(Origin::signed(1), kitty, b"Tommy".to_vec()));
+            assert_eq!(KittyModule::name_of(kitty), Some(b"Tommy".to_vec()));
+            assert_eq!(KittyModule::kitty_by_name(b"Tom".to_vec()), None);
+            assert_eq!(balances::Module::<Test>::reserved_balance(&1), 50);
+
+            assert_ok!(KittyModule::clear_name(Origin::signed(1), kitty));
+            assert_eq!(KittyModule::kitty_by_name(b"Tommy".to_vec()), None);
+            assert_eq!(balances::Module::<Test>::reserved_balance(&1), 0);
+            assert_eq!(balances::Module::<Test>::free_balance(&1), 1_000);
+        });
+    }
+
+    #[test]
+    fn set_name_enforces_length_and_uniqueness() {
+        with_externalities(&mut new_test_ext(), || {
+            let kitty_1 = create_kitty(1);
+            let kitty_2 = create_kitty(2);
+
+            assert_noop!(KittyModule::set_name(Origin::signed(1), kitty_1, b"Al".to_vec()), "This name is too short");
+            assert_noop!(KittyModule::set_name(Origin::signed(1), kitty_1, b"Alexander".to_vec()), "This name is too long");
+            assert_noop!(KittyModule::set_name(Origin::signed(2), kitty_1, b"Tom".to_vec()), "You do not own this kitty");
+
+            assert_ok!(KittyModule::set_name(Origin::signed(1), kitty_1, b"Tom".to_vec()));
+            assert_ok!(KittyModule::set_name(Origin::signed(1), kitty_1, b"Tom".to_vec()));
+            assert_noop!(KittyModule::set_name(Origin::signed(2), kitty_2, b"Tom".to_vec()), "This name is already taken");
+        });
+    }
+
+    #[test]
+    fn set_metadata_adds_to_the_deposit() {
+        with_externalities(&mut new_test_ext(), || {
+            let kitty = create_kitty(1);
+            let content_hash = H256::repeat_byte(7);
+
+            assert_noop!(
+                KittyModule::set_metadata(Origin::signed(1), kitty, b"ipfs://0123456789abcdef".to_vec(), content_hash),
+                "The metadata URI is too long"
+            );
+
+            assert_ok!(KittyModule::set_name(Origin::signed(1), kitty, b"Tom".to_vec()));
+            assert_ok!(KittyModule::set_metadata(Origin::signed(1), kitty, b"ipfs://tom".to_vec(), content_hash));
+            assert_eq!(
+                KittyModule::metadata_of(kitty),
+                Some(KittyMetadata { uri: b"ipfs://tom".to_vec(), content_hash })
+            );
+            assert_eq!(balances::Module::<Test>::reserved_balance(&1), 130);
+
+            assert_ok!(KittyModule::clear_metadata(Origin::signed(1), kitty));
+            assert_eq!(KittyModule::metadata_of(kitty), None);
+            assert_eq!(balances::Module::<Test>::reserved_balance(&1), 30);
+        });
+    }
+
+    #[test]
+    fn renaming_returns_the_deposit_to_the_previous_owner() {
+        with_externalities(&mut new_test_ext(), || {
+            let kitty = create_kitty(1);
+
+            assert_ok!(KittyModule::set_name(Origin::signed(1), kitty, b"Tom".to_vec()));
+            assert_ok!(KittyModule::transfer(Origin::signed(1), 2, kitty));
+            assert_eq!(KittyModule::name_of(kitty), Some(b"Tom".to_vec()));
+
+            assert_ok!(KittyModule::set_name(Origin::signed(2), kitty, b"Felix".to_vec()));
+            assert_eq!(balances::Module::<Test>::reserved_balance(&1), 0);
+            assert_eq!(balances::Module::<Test>::reserved_balance(&2), 50);
+            assert_eq!(KittyModule::deposit_of(kitty), Some((2, 50)));
+
+            assert_ok!(KittyModule::burn_kitty(Origin::signed(2), kitty));
+            assert_eq!(KittyModule::kitty_by_name(b"Felix".to_vec()), None);
+            assert_eq!(balances::Module::<Test>::reserved_balance(&2), 0);
+        });
+    }
+
+    #[test]
+    fn approved_account_can_transfer_once() {
+        with_externalities(&mut new_test_ext(), || {
+            let kitty = create_kitty(1);
+
+            assert_noop!(
+                KittyModule::transfer_from_approved(Origin::signed(2), 1, 3, kitty),
+                "You are not approved to transfer this kitty"
+            );
+            assert_noop!(
+                KittyModule::approve(Origin::signed(2), 2, kitty),
+                "You are neither the owner nor an operator of this kitty"
+            );
+
+            assert_ok!(KittyModule::approve(Origin::signed(1), 2, kitty));
+            assert_eq!(KittyModule::approved_for(kitty), Some(2));
+            assert_noop!(
+                KittyModule::transfer_from_approved(Origin::signed(2), 3, 2, kitty),
+                "from account does not own this kitty"
+            );
+            assert_ok!(KittyModule::transfer_from_approved(Origin::signed(2), 1, 3, kitty));
+            assert_integrity();
+
+            assert_eq!(KittyModule::owner_of(kitty), Some(3));
+            assert_eq!(KittyModule::approved_for(kitty), None);
+            assert_noop!(
+                KittyModule::transfer_from_approved(Origin::signed(2), 3, 2, kitty),
+                "You are not approved to transfer this kitty"
+            );
+        });
+    }
+
+    #[test]
+    fn approval_is_cleared_on_transfer() {
+        with_externalities(&mut new_test_ext(), || {
+            let kitty = create_kitty(1);
+
+            assert_ok!(KittyModule::approve(Origin::signed(1), 2, kitty));
+            assert_ok!(KittyModule::transfer(Origin::signed(1), 3, kitty));
+
+            assert_eq!(KittyModule::approved_for(kitty), None);
+        });
+    }
+
+    #[test]
+    fn operator_can_move_and_approve_every_kitty() {
+        with_externalities(&mut new_test_ext(), || {
+            let kitty_1 = create_kitty(1);
+            let kitty_2 = create_kitty(1);
+
+            assert_noop!(
+                KittyModule::set_approval_for_all(Origin::signed(1), 1, true),
+                "You can't approve yourself as an operator"
+            );
+            assert_ok!(KittyModule::set_approval_for_all(Origin::signed(1), 2, true));
+            assert!(KittyModule::is_approved_for_all(&1, &2));
+
+            assert_ok!(KittyModule::transfer_from_approved(Origin::signed(2), 1, 2, kitty_1));
+            assert_integrity();
+            assert_ok!(KittyModule::approve(Origin::signed(2), 3, kitty_2));
+            assert_ok!(KittyModule::transfer_from_approved(Origin::signed(3), 1, 3, kitty_2));
+            assert_integrity();
+            assert_eq!(KittyModule::owner_of(kitty_1), Some(2));
+            assert_eq!(KittyModule::owner_of(kitty_2), Some(3));
+
+            assert_ok!(KittyModule::set_approval_for_all(Origin::signed(1), 2, false));
+            assert!(!KittyModule::is_approved_for_all(&1, &2));
+        });
+    }
+
+    #[test]
+    fn transfer_batch_moves_every_kitty() {
+        with_externalities(&mut new_test_ext(), || {
+            let kitty_1 = create_kitty(1);
+            let kitty_2 = create_kitty(1);
+            let kitty_3 = create_kitty(1);
+
+            assert_ok!(KittyModule::transfer_batch(Origin::signed(1), 2, vec![kitty_1, kitty_3]));
+            assert_integrity();
+
+            assert_eq!(KittyModule::owned_kitty_count(1), 1);
+            assert_eq!(KittyModule::owned_kitty_count(2), 2);
+            assert_eq!(KittyModule::owner_of(kitty_1), Some(2));
+            assert_eq!(KittyModule::owner_of(kitty_2), Some(1));
+            assert_eq!(KittyModule::owner_of(kitty_3), Some(2));
+        });
+    }
+
+    #[test]
+    fn transfer_batch_is_all_or_nothing() {
+        with_externalities(&mut new_test_ext(), || {
+            let kitty_1 = create_kitty(1);
+            let kitty_2 = create_kitty(1);
+            let other = create_kitty(3);
+
+            assert_noop!(
+                KittyModule::transfer_batch(Origin::signed(1), 2, vec![kitty_1, kitty_2, other]),
+                "You do not own this kitty"
+            );
+            assert_noop!(
+                KittyModule::transfer_batch(Origin::signed(1), 2, vec![kitty_1, kitty_2, kitty_1]),
+                "The batch contains the same kitty twice"
+            );
+            assert_noop!(
+                KittyModule::transfer_batch(Origin::signed(1), 2, vec![kitty_1, kitty_2, kitty_1, kitty_2]),
+                "The batch is too large"
+            );
+            assert_noop!(KittyModule::transfer_batch(Origin::signed(1), 2, vec![]), "The batch is empty");
+        });
+    }
+
+    #[test]
+    fn mint_batch_is_root_only_and_bounded() {
+        with_externalities(&mut new_test_ext(), || {
+            assert_noop!(
+                KittyModule::mint_batch(Origin::signed(1), 1, 3),
+                "bad origin: expected to be a root origin"
+            );
+            assert_noop!(KittyModule::mint_batch(Origin::ROOT, 1, 4), "The batch is too large");
+
+            assert_ok!(KittyModule::mint_batch(Origin::ROOT, 2, 3));
+
+            assert_eq!(KittyModule::owned_kitty_count(2), 3);
+            assert_eq!(KittyModule::all_kitties_count(), 3);
+            assert_eq!(KittyModule::creator_of(KittyModule::kitty_of_owner_by_index((2, 2))), Some(2));
+        });
+    }
+
+    #[test]
+    fn max_supply_stops_minting() {
+        with_externalities(&mut new_test_ext(), || {
+            create_kitty(1);
+
+            assert_noop!(
+                KittyModule::set_max_supply(Origin::signed(1), Some(2)),
+                "bad origin: expected to be a root origin"
+            );
+            assert_ok!(KittyModule::set_max_supply(Origin::ROOT, Some(2)));
+
+            create_kitty(2);
+            assert_noop!(
+                KittyModule::commit_create(Origin::signed(3), commitment(3)),
+                "The maximum supply of kitties has been reached"
+            );
+            assert_noop!(
+                KittyModule::mint_batch(Origin::ROOT, 3, 1),
+                "The maximum supply of kitties has been reached"
+            );
+
+            assert_ok!(KittyModule::set_max_supply(Origin::ROOT, None));
+            create_kitty(3);
+        });
+    }
+
+    #[test]
+    fn max_owned_per_account_limits_receiving() {
+        with_externalities(&mut new_test_ext(), || {
+            let kitty_1 = create_kitty(1);
+            let kitty_2 = create_kitty(2);
+            assert_ok!(KittyModule::set_max_owned_per_account(Origin::ROOT, Some(1)));
+
+            assert_noop!(
+                KittyModule::commit_create(Origin::signed(1), commitment(1)),
+                "The account would own more kitties than allowed per account"
+            );
+            assert_noop!(
+                KittyModule::transfer(Origin::signed(2), 1, kitty_2),
+                "The account would own more kitties than allowed per account"
+            );
+            assert_ok!(KittyModule::transfer(Origin::signed(1), 1, kitty_1));
+            assert_ok!(KittyModule::transfer(Origin::signed(1), 3, kitty_1));
+        });
+    }
+
+    #[test]
+    fn buy_kitty_checks_the_limit_before_paying() {
+        with_externalities(&mut new_test_ext(), || {
+            create_kitty(1);
+            let kitty = create_kitty(2);
+            assert_ok!(KittyModule::set_price(Origin::signed(2), kitty, 100));
+            assert_ok!(KittyModule::set_max_owned_per_account(Origin::ROOT, Some(1)));
+
+            assert_noop!(
+                KittyModule::buy_kitty(Origin::signed(1), kitty, 100),
+                "The account would own more kitties than allowed per account"
+            );
+            assert_eq!(balances::Module::<Test>::free_balance(&1), 1_000);
+        });
+    }
+
+    #[test]
+    fn mint_fee_is_burned_by_default() {
+        with_externalities(&mut new_test_ext(), || {
+            assert_noop!(
+                KittyModule::set_mint_fee(Origin::signed(1), 10),
+                "bad origin: expected to be a root origin"
+            );
+            assert_ok!(KittyModule::set_mint_fee(Origin::ROOT, 10));
+            let issuance = balances::Module::<Test>::total_issuance();
+
+            commit_and_create(1);
+
+            assert_eq!(balances::Module::<Test>::free_balance(&1), 990);
+            assert_eq!(balances::Module::<Test>::reserved_balance(&1), 0);
+            assert_eq!(balances::Module::<Test>::total_issuance(), issuance - 10);
+        });
+    }
+
+    #[test]
+    fn fees_can_go_to_a_treasury_account() {
+        with_externalities(&mut new_test_ext(), || {
+            assert_ok!(KittyModule::set_fee_destination(Origin::ROOT, FeeDestination::Account(3)));
+            assert_ok!(KittyModule::set_mint_fee(Origin::ROOT, 10));
+            assert_ok!(KittyModule::set_breed_fee(Origin::ROOT, 25));
+
+            commit_and_create(1);
+            let kitty_1 = create_female(1);
+            let kitty_2 = create_male(1);
+            breed_kitty(1, kitty_1, kitty_2);
+
+            assert_eq!(balances::Module::<Test>::free_balance(&1), 965);
+            assert_eq!(balances::Module::<Test>::free_balance(&3), 1_035);
+        });
+    }
+
+    #[test]
+    fn create_kitty_fails_without_funds_for_the_fee() {
+        with_externalities(&mut new_test_ext(), || {
+            assert_ok!(KittyModule::set_mint_fee(Origin::ROOT, 10));
+
+            assert!(KittyModule::commit_create(Origin::signed(4), commitment(4)).is_err());
+            assert_eq!(KittyModule::create_commitment(&4, &commitment(4)), None);
+            assert_eq!(KittyModule::all_kitties_count(), 0);
+        });
+    }
+
+    #[test]
+    fn block_author_destination_needs_a_known_author() {
+        with_externalities(&mut new_test_ext(), || {
+            // モックではブロック作成者が分からない
+            assert_noop!(
+                KittyModule::set_fee_destination(Origin::ROOT, FeeDestination::BlockAuthor),
+                "This runtime can't find the block author"
+            );
+            assert_eq!(KittyModule::fee_destination(), FeeDestination::Burn);
+        });
+    }
+
+    #[test]
+    fn for_sale_index_follows_listings() {
+        with_externalities(&mut new_test_ext(), || {
+            let cheap = create_kitty(1);
+            let pricey = create_kitty(1);
+            let unlisted = create_kitty(1);
+
+            assert_ok!(KittyModule::set_price(Origin::signed(1), pricey, 500));
+            assert_ok!(KittyModule::set_price(Origin::signed(1), cheap, 5));
+            assert_eq!(KittyModule::for_sale_count(), 2);
+            let ids = |kitties: Vec<Kitty<H256, u64, u64>>| kitties.into_iter().map(|kitty| kitty.id).collect::<Vec<_>>();
+            assert_eq!(ids(KittyModule::kitties_for_sale(0, 10)), vec![cheap, pricey]);
+            assert_eq!(ids(KittyModule::kitties_for_sale(1, 10)), vec![pricey]);
+            assert_eq!(ids(KittyModule::kitties_for_sale(0, 1)), vec![cheap]);
+            assert!(KittyModule::for_sale_position(unlisted).is_none());
+
+            assert_ok!(KittyModule::buy_kitty(Origin::signed(2), cheap, 5));
+            assert_eq!(ids(KittyModule::kitties_for_sale(0, 10)), vec![pricey]);
+
+            assert_ok!(KittyModule::set_price(Origin::signed(1), pricey, 0));
+            assert_eq!(KittyModule::for_sale_count(), 0);
+
+            assert_ok!(KittyModule::set_price(Origin::signed(1), unlisted, 7));
+            assert_ok!(KittyModule::burn_kitty(Origin::signed(1), unlisted));
+            assert_eq!(KittyModule::for_sale_count(), 0);
+            assert!(KittyModule::kitties_for_sale(0, 10).is_empty());
+        });
+    }
+
+    #[test]
+    fn kitties_for_sale_below_filters_by_price() {
+        with_externalities(&mut new_test_ext(), || {
+            let kitty_1 = create_kitty(1);
+            let kitty_2 = create_kitty(1);
+            let kitty_3 = create_kitty(1);
+            let kitty_4 = create_kitty(1);
+            assert_ok!(KittyModule::set_price(Origin::signed(1), kitty_1, 3));
+            assert_ok!(KittyModule::set_price(Origin::signed(1), kitty_2, 40));
+            assert_ok!(KittyModule::set_price(Origin::signed(1), kitty_3, 33));
+            assert_ok!(KittyModule::set_price(Origin::signed(1), kitty_4, 60));
+
+            let ids = |kitties: Vec<Kitty<H256, u64, u64>>| kitties.into_iter().map(|kitty| kitty.id).collect::<Vec<_>>();
+            // 33、40、60は同じ価格帯に入る
+            assert_eq!(ids(KittyModule::kitties_for_sale_below(40, 0, 10)), vec![kitty_1, kitty_2, kitty_3]);
+            assert_eq!(ids(KittyModule::kitties_for_sale_below(40, 1, 1)), vec![kitty_2]);
+            assert_eq!(ids(KittyModule::kitties_for_sale_below(40, 2, 10)), vec![kitty_3]);
+            assert_eq!(ids(KittyModule::kitties_for_sale_below(2, 0, 10)), vec![]);
+            assert_eq!(ids(KittyModule::kitties_for_sale_below(1_000, 0, 10)).len(), 4);
+        });
+    }
+
+    #[test]
+    fn kitties_for_sale_below_uses_the_current_dutch_auction_price() {
+        with_externalities(&mut new_test_ext(), || {
+            let fixed = create_kitty(1);
+            let dutch = create_kitty(1);
+            assert_ok!(KittyModule::set_price(Origin::signed(1), fixed, 50));
+            // 10ブロックかけて1,000から100まで下がる
+            assert_ok!(KittyModule::create_dutch_auction(Origin::signed(1), dutch, 1_000, 100, 10));
+
+            let ids = |kitties: Vec<Kitty<H256, u64, u64>>| kitties.into_iter().map(|kitty| kitty.id).collect::<Vec<_>>();
+            assert_eq!(ids(KittyModule::kitties_for_sale_below(500, 0, 10)), vec![fixed]);
+            assert_eq!(ids(KittyModule::kitties_for_sale_below(1_000, 0, 10)), vec![fixed, dutch]);
+
+            system::Module::<Test>::set_block_number(6);
+            assert_eq!(KittyModule::current_price(dutch), 460);
+            assert_eq!(ids(KittyModule::kitties_for_sale_below(500, 0, 10)), vec![fixed, dutch]);
+            assert_eq!(ids(KittyModule::kitties_for_sale_below(500, 1, 10)), vec![dutch]);
+            assert_eq!(ids(KittyModule::kitties_for_sale_below(400, 0, 10)), vec![fixed]);
+
+            // 販売が終われば価格帯からも外れる
+            assert_ok!(KittyModule::buy_kitty(Origin::signed(2), dutch, 500));
+            assert_eq!(KittyModule::for_sale_count(), 1);
+            assert_integrity();
+        });
+    }
+
+    #[test]
+    fn listing_expires_in_on_initialize() {
+        with_externalities(&mut new_test_ext(), || {
+            let kitty = create_kitty(1);
+
+            assert_noop!(
+                KittyModule::list_for_sale(Origin::signed(1), kitty, 0, 5),
+                "The price must be greater than zero"
+            );
+            assert_noop!(
+                KittyModule::list_for_sale(Origin::signed(1), kitty, 100, 0),
+                "The listing must expire in the future"
+            );
+            assert_ok!(KittyModule::list_for_sale(Origin::signed(1), kitty, 100, 5));
+            assert_eq!(KittyModule::listing_expires_at(kitty), Some(5));
+
+            KittyModule::on_initialize(4);
+            assert_eq!(KittyModule::kitty(kitty).price, 100);
+
+            KittyModule::on_initialize(5);
+            assert_eq!(KittyModule::kitty(kitty).price, 0);
+            assert_eq!(KittyModule::listing_expires_at(kitty), None);
+            assert_eq!(KittyModule::for_sale_count(), 0);
+        });
+    }
+
+    #[test]
+    fn relisting_replaces_the_expiry() {
+        with_externalities(&mut new_test_ext(), || {
+            let kitty = create_kitty(1);
+
+            assert_ok!(KittyModule::list_for_sale(Origin::signed(1), kitty, 100, 5));
+            assert_ok!(KittyModule::set_price(Origin::signed(1), kitty, 80));
+            assert_eq!(KittyModule::listing_expires_at(kitty), None);
+
+            KittyModule::on_initialize(5);
+            assert_eq!(KittyModule::kitty(kitty).price, 80);
+        });
+    }
+
+    #[test]
+    fn listing_expiry_work_is_bounded_per_block() {
+        with_externalities(&mut new_test_ext(), || {
+            let kitties: Vec<_> = (0..3).map(|_| create_kitty(1)).collect();
+            for kitty in &kitties {
+                assert_ok!(KittyModule::list_for_sale(Origin::signed(1), *kitty, 100, 5));
+            }
+
+            KittyModule::on_initialize(5);
+            assert_eq!(KittyModule::for_sale_count(), 1);
+
+            KittyModule::on_initialize(6);
+            assert_eq!(KittyModule::for_sale_count(), 0);
+        });
+    }
+
+    #[test]
+    fn delist_and_transfer_end_the_listing() {
+        with_externalities(&mut new_test_ext(), || {
+            let kitty = create_kitty(1);
+
+            assert_noop!(KittyModule::delist(Origin::signed(1), kitty), "This kitty is not for sale");
+            assert_ok!(KittyModule::list_for_sale(Origin::signed(1), kitty, 100, 5));
+            assert_noop!(KittyModule::delist(Origin::signed(2), kitty), "You do not own this kitty");
+            assert_ok!(KittyModule::delist(Origin::signed(1), kitty));
+            assert_eq!(KittyModule::kitty(kitty).price, 0);
+            assert_eq!(KittyModule::listing_expires_at(kitty), None);
+
+            assert_ok!(KittyModule::set_price(Origin::signed(1), kitty, 100));
+            assert_ok!(KittyModule::transfer(Origin::signed(1), 2, kitty));
+            assert_integrity();
+            assert_eq!(KittyModule::kitty(kitty).price, 0);
+            assert_eq!(KittyModule::for_sale_count(), 0);
+        });
+    }
+
+    #[test]
+    fn transfer_to_self_keeps_the_owned_count() {
+        with_externalities(&mut new_test_ext(), || {
+            let kitty_1 = create_kitty(1);
+            let kitty_2 = create_kitty(1);
+
+            assert_ok!(KittyModule::transfer(Origin::signed(1), 1, kitty_1));
+            assert_integrity();
+            assert_eq!(KittyModule::owned_kitty_count(1), 2);
+            assert_eq!(KittyModule::kitty_of_owner_by_index((1, 0)), kitty_2);
+            assert_eq!(KittyModule::kitty_of_owner_by_index((1, 1)), kitty_1);
+        });
+    }
+
+    #[test]
+    fn check_integrity_reports_every_inconsistency() {
+        with_externalities(&mut new_test_ext(), || {
+            let kitty_1 = create_kitty(1);
+            let kitty_2 = create_kitty(2);
+
+            // 所有数のずれと、所有者と一致しない所有リスト
+            <OwnedKittiesCount<Test>>::insert(1, 2);
+            <OwnedKittiesArray<Test>>::insert((1, 1), kitty_2);
+            // 存在しないKittyを指す全体リスト
+            let dangling = H256::repeat_byte(7);
+            <AllKittiesArray<Test>>::insert(2, dangling);
+            <AllKittiesIndex<Test>>::insert(dangling, 2);
+            <KittyOwner<Test>>::insert(dangling, 3);
+            <AllKittiesCount<Test>>::put(3);
+
+            let errors = KittyModule::check_integrity().unwrap_err();
+            assert_eq!(errors.len(), 5, "{:?}", errors);
+            assert!(errors.iter().any(|e| e.contains("dangling kitty id")));
+            assert!(errors.iter().any(|e| e.starts_with("OwnedKittiesCount[1] is 2")));
+            assert!(errors.iter().any(|e| e.starts_with("OwnedKittiesArray[(1, 1)]")));
+            assert!(errors.iter().any(|e| e.starts_with("OwnedKittiesCount[3] is 0")));
+            assert!(errors.iter().any(|e| e.starts_with(&format!("OwnedKittiesIndex[{:?}] is 0", dangling))));
+            assert_eq!(KittyModule::owner_of(kitty_1), Some(1));
+        });
+    }
+
+    #[test]
+    fn reveal_create_mints_a_gen0_kitty() {
+        with_externalities(&mut new_test_ext(), || {
+            let kitty_id = commit_and_create(1);
+
+            assert_eq!(last_event(), TestEvent::kitties(RawEvent::Created(1, kitty_id)));
+            assert_eq!(KittyModule::owner_of(kitty_id), Some(1));
+            assert_eq!(KittyModule::creator_of(kitty_id), Some(1));
+            assert_eq!(KittyModule::all_kitties_count(), 1);
+            assert_eq!(KittyModule::owned_kitty_count(1), 1);
+            assert_eq!(KittyModule::kitty_by_index(0), kitty_id);
+
+            let kitty = KittyModule::kitty(kitty_id);
+            assert_eq!(kitty.id, kitty_id);
+            assert_eq!(kitty.price, 0);
+            assert_eq!(kitty.gen, 0);
+            // 各遺伝子は優性の対立遺伝子が先に来る
+            let dna = kitty.dna.as_bytes();
+            assert!((0..genetics::GENE_COUNT).all(|gene| dna[2 * gene] <= dna[2 * gene + 1]));
+
+            assert_ne!(create_kitty(1), kitty_id);
+            assert_eq!(KittyModule::owned_kitty_count(1), 2);
+        });
+    }
+
+    #[test]
+    fn ext_builder_pre_mints_kitties() {
+        let mut ext = ExtBuilder::default()
+            .balances(vec![(1, 50)])
+            .kitties(vec![(1, 2), (2, 1)])
+            .build();
+        with_externalities(&mut ext, || {
+            assert_eq!(KittyModule::all_kitties_count(), 3);
+            assert_eq!(KittyModule::owned_kitty_count(1), 2);
+            assert_eq!(KittyModule::owned_kitty_count(2), 1);
+            assert_eq!(<balances::Module<Test>>::free_balance(&1), 50);
+            assert_eq!(<balances::Module<Test>>::free_balance(&2), 0);
+            assert_integrity();
+        });
+    }
+
+    #[test]
+    fn set_price_lists_the_kitty() {
+        with_externalities(&mut ExtBuilder::default().kitties(vec![(1, 1)]).build(), || {
+            let kitty_id = KittyModule::kitty_of_owner_by_index((1, 0));
+
+            assert_noop!(
+                KittyModule::set_price(Origin::signed(1), H256::zero(), 100),
+                "This cat does not exist"
+            );
+            assert_noop!(KittyModule::set_price(Origin::signed(2), kitty_id, 100), "You do not own this cat");
+
+            assert_ok!(KittyModule::set_price(Origin::signed(1), kitty_id, 100));
+            assert_eq!(last_event(), TestEvent::kitties(RawEvent::PriceSet(1, kitty_id, 100)));
+            assert_eq!(KittyModule::kitty(kitty_id).price, 100);
+
+            // 価格を0にすると売りに出されていない状態に戻る
+            assert_ok!(KittyModule::set_price(Origin::signed(1), kitty_id, 0));
+            assert_eq!(KittyModule::for_sale_count(), 0);
+        });
+    }
+
+    #[test]
+    fn transfer_moves_ownership() {
+        with_externalities(&mut ExtBuilder::default().kitties(vec![(1, 2)]).build(), || {
+            let kitty_1 = KittyModule::kitty_of_owner_by_index((1, 0));
+            let kitty_2 = KittyModule::kitty_of_owner_by_index((1, 1));
+
+            assert_noop!(KittyModule::transfer(Origin::signed(2), 3, kitty_1), "You do not own this kitty");
+            assert_noop!(
+                KittyModule::transfer(Origin::signed(1), 2, H256::zero()),
+                "No owner for this kitty"
+            );
+
+            assert_ok!(KittyModule::transfer(Origin::signed(1), 2, kitty_1));
+            assert_integrity();
+            assert_eq!(last_event(), TestEvent::kitties(RawEvent::Transferred(1, 2, kitty_1)));
+
+            assert_eq!(KittyModule::owner_of(kitty_1), Some(2));
+            assert_eq!(KittyModule::owned_kitty_count(1), 1);
+            assert_eq!(KittyModule::owned_kitty_count(2), 1);
+            // swap and popで残ったKittyが先頭に詰められる
+            assert_eq!(KittyModule::kitty_of_owner_by_index((1, 0)), kitty_2);
+            assert_eq!(KittyModule::kitty_of_owner_by_index((2, 0)), kitty_1);
+        });
+    }
+
+    #[test]
+    fn buy_kitty_pays_the_owner() {
+        with_externalities(&mut ExtBuilder::default().kitties(vec![(1, 1)]).build(), || {
+            let kitty_id = KittyModule::kitty_of_owner_by_index((1, 0));
+            assert_ok!(KittyModule::set_price(Origin::signed(1), kitty_id, 100));
+
+            assert_ok!(KittyModule::buy_kitty(Origin::signed(2), kitty_id, 150));
+            assert_integrity();
+
+            assert_eq!(KittyModule::owner_of(kitty_id), Some(2));
+            assert_eq!(KittyModule::kitty(kitty_id).price, 0);
+            assert_eq!(<balances::Module<Test>>::free_balance(&1), 1_100);
+            assert_eq!(<balances::Module<Test>>::free_balance(&2), 900);
+
+            // 売却ではDelistedは発行されない
+            let events = kitty_events();
+            assert_eq!(&events[events.len() - 2..], &[
+                RawEvent::Transferred(1, 2, kitty_id),
+                RawEvent::Bought(2, 1, kitty_id, 100),
+            ]);
+        });
+    }
+
+    #[test]
+    fn buy_kitty_rejects_invalid_purchases() {
+        let mut ext = ExtBuilder::default()
+            .balances(vec![(1, 1_000), (2, 1_000), (3, 10)])
+            .kitties(vec![(1, 2)])
+            .build();
+        with_externalities(&mut ext, || {
+            let kitty_1 = KittyModule::kitty_of_owner_by_index((1, 0));
+            let kitty_2 = KittyModule::kitty_of_owner_by_index((1, 1));
+
+            assert_noop!(
+                KittyModule::buy_kitty(Origin::signed(2), H256::zero(), 100),
+                "This cat does not exits"
+            );
+            assert_noop!(
+                KittyModule::buy_kitty(Origin::signed(2), kitty_1, 100),
+                "This cat you want to buy is not for sale"
+            );
+
+            assert_ok!(KittyModule::set_price(Origin::signed(1), kitty_1, 100));
+            assert_noop!(KittyModule::buy_kitty(Origin::signed(1), kitty_1, 100), "You can't buy your own cat");
+            assert_noop!(
+                KittyModule::buy_kitty(Origin::signed(2), kitty_1, 99),
+                "The cat you want to buy is costs more than your max price"
+            );
+            assert_noop!(
+                KittyModule::buy_kitty(Origin::signed(3), kitty_1, 100),
+                "You don't have enough free balance to buy this cat"
+            );
+
+            // 所持金をちょうど使い切る価格なら買える
+            assert_ok!(KittyModule::set_price(Origin::signed(1), kitty_2, 10));
+            assert_ok!(KittyModule::buy_kitty(Origin::signed(3), kitty_2, 10));
+            assert_eq!(<balances::Module<Test>>::free_balance(&3), 0);
+            assert_eq!(KittyModule::owner_of(kitty_2), Some(3));
+        });
+    }
+
+    #[test]
+    fn breed_kitty_emits_created_and_bred() {
+        with_externalities(&mut new_test_ext(), || {
+            let kitty_1 = create_female(1);
+            let kitty_2 = create_male(1);
+
+            let child = breed_kitty(1, kitty_1, kitty_2);
+
+            let events = kitty_events();
+            assert_eq!(&events[events.len() - 2..], &[
+                RawEvent::Created(1, child),
+                RawEvent::Bred(1, child, kitty_1, kitty_2),
+            ]);
+            assert_eq!(KittyModule::owned_kitty_count(1), 3);
+            assert_eq!(KittyModule::all_kitties_count(), 3);
+        });
+    }
+
+    #[test]
+    fn commit_create_detects_overflow() {
+        with_externalities(&mut new_test_ext(), || {
+            <AllKittiesCount<Test>>::put(u64::max_value());
+            assert_noop!(
+                KittyModule::commit_create(Origin::signed(1), commitment(1)),
+                "overflow adding a new kitty to total supply"
+            );
+
+            <AllKittiesCount<Test>>::put(0);
+            <OwnedKittiesCount<Test>>::insert(1, u64::max_value());
+            assert_noop!(
+                KittyModule::commit_create(Origin::signed(1), commitment(1)),
+                "overflow adding a new kitty to owned kitty count"
+            );
+        });
+    }
+
+    #[test]
+    fn transfer_and_buy_detect_owned_count_overflow() {
+        with_externalities(&mut ExtBuilder::default().kitties(vec![(1, 1)]).build(), || {
+            let kitty_id = KittyModule::kitty_of_owner_by_index((1, 0));
+            <OwnedKittiesCount<Test>>::insert(2, u64::max_value());
+
+            assert_noop!(
+                KittyModule::transfer(Origin::signed(1), 2, kitty_id),
+                "overflow adding a new kitty to owned kitty count"
+            );
+
+            assert_ok!(KittyModule::set_price(Origin::signed(1), kitty_id, 100));
+            assert_noop!(
+                KittyModule::buy_kitty(Origin::signed(2), kitty_id, 100),
+                "overflow adding a new kitty to owned kitty count"
+            );
+            assert_eq!(<balances::Module<Test>>::free_balance(&2), 1_000);
+        });
+    }
+
+    #[test]
+    fn burn_kitty_removes_the_kitty_everywhere() {
+        with_externalities(&mut ExtBuilder::default().kitties(vec![(1, 3)]).build(), || {
+            let kitty_1 = KittyModule::kitty_of_owner_by_index((1, 0));
+            let kitty_3 = KittyModule::kitty_of_owner_by_index((1, 2));
+
+            assert_ok!(KittyModule::set_name(Origin::signed(1), kitty_1, b"tama".to_vec()));
+            assert_ok!(KittyModule::set_price(Origin::signed(1), kitty_1, 100));
+            assert_ok!(KittyModule::make_offer(Origin::signed(2), kitty_1, 50, 10));
+            assert_balances(1, 960, 40);
+            assert_balances(2, 950, 50);
+            assert_integrity();
+
+            assert_noop!(KittyModule::burn_kitty(Origin::signed(2), kitty_1), "You do not own this kitty");
+            assert_noop!(KittyModule::burn_kitty(Origin::signed(1), H256::zero()), "No owner for this kitty");
+
+            assert_ok!(KittyModule::burn_kitty(Origin::signed(1), kitty_1));
+            assert_integrity();
+            assert_eq!(last_event(), TestEvent::kitties(RawEvent::Burned(1, kitty_1)));
+
+            assert!(!<Kitties<Test>>::exists(kitty_1));
+            assert_eq!(KittyModule::owner_of(kitty_1), None);
+            assert_eq!(KittyModule::creator_of(kitty_1), None);
+            assert_eq!(KittyModule::all_kitties_count(), 2);
+            assert_eq!(KittyModule::owned_kitty_count(1), 2);
+            // swap and popで最後のKittyが空いた位置に詰められる
+            assert_eq!(KittyModule::kitty_by_index(0), kitty_3);
+            assert_eq!(KittyModule::kitty_of_owner_by_index((1, 0)), kitty_3);
+
+            // 販売、オファー、名前が片付けられ、確保されていた資金が返却される
+            assert_eq!(KittyModule::for_sale_count(), 0);
+            assert_eq!(KittyModule::offer_of(&kitty_1, &2), None);
+            assert!(KittyModule::offer_bidders(kitty_1).is_empty());
+            assert_eq!(KittyModule::kitty_by_name(b"tama".to_vec()), None);
+            assert_balances(1, 1_000, 0);
+            assert_balances(2, 1_000, 0);
+        });
+    }
+
+    #[test]
+    fn burn_kitty_is_blocked_during_an_auction() {
+        with_externalities(&mut ExtBuilder::default().kitties(vec![(1, 1)]).build(), || {
+            let kitty_id = KittyModule::kitty_of_owner_by_index((1, 0));
+            assert_ok!(KittyModule::create_auction(Origin::signed(1), kitty_id, 100, 5));
+
+            assert_noop!(KittyModule::burn_kitty(Origin::signed(1), kitty_id), "This kitty is locked in an auction");
+            assert_eq!(KittyModule::all_kitties_count(), 1);
+            assert_integrity();
+        });
+    }
+
+    #[test]
+    fn bid_reserves_funds_and_refunds_the_outbid_bidder() {
+        with_externalities(&mut ExtBuilder::default().kitties(vec![(1, 1)]).build(), || {
+            let kitty_id = KittyModule::kitty_of_owner_by_index((1, 0));
+            assert_ok!(KittyModule::create_auction(Origin::signed(1), kitty_id, 100, 5));
+            assert_eq!(last_event(), TestEvent::kitties(RawEvent::AuctionCreated(1, kitty_id, 100, 5)));
+
+            assert_noop!(KittyModule::bid(Origin::signed(1), kitty_id, 100), "You can't bid on your own kitty");
+            assert_noop!(KittyModule::bid(Origin::signed(2), kitty_id, 99), "The bid is below the reserve price");
+
+            assert_ok!(KittyModule::bid(Origin::signed(2), kitty_id, 100));
+            assert_integrity();
+            assert_balances(2, 900, 100);
+
+            assert_noop!(
+                KittyModule::bid(Origin::signed(3), kitty_id, 100),
+                "The bid must be higher than the current highest bid"
+            );
+
+            // 上回る入札があると、以前の最高入札者の資金は返却される
+            assert_ok!(KittyModule::bid(Origin::signed(3), kitty_id, 150));
+            assert_integrity();
+            assert_eq!(last_event(), TestEvent::kitties(RawEvent::BidPlaced(3, kitty_id, 150)));
+            assert_balances(2, 1_000, 0);
+            assert_balances(3, 850, 150);
+
+            system::Module::<Test>::set_block_number(6);
+            assert_noop!(KittyModule::bid(Origin::signed(2), kitty_id, 200), "This auction has already ended");
+        });
+    }
+
+    #[test]
+    fn settle_auction_pays_the_seller_and_transfers_the_kitty() {
+        with_externalities(&mut ExtBuilder::default().kitties(vec![(1, 1)]).build(), || {
+            let kitty_id = KittyModule::kitty_of_owner_by_index((1, 0));
+            assert_ok!(KittyModule::create_auction(Origin::signed(1), kitty_id, 100, 5));
+            assert_ok!(KittyModule::bid(Origin::signed(2), kitty_id, 100));
+            assert_ok!(KittyModule::bid(Origin::signed(3), kitty_id, 150));
+
+            system::Module::<Test>::set_block_number(5);
+            KittyModule::on_finalize(5);
+            assert_integrity();
+
+            assert_eq!(last_event(), TestEvent::kitties(RawEvent::AuctionSettled(1, 3, kitty_id, 150)));
+            assert_eq!(KittyModule::owner_of(kitty_id), Some(3));
+            assert_eq!(KittyModule::auction_of(kitty_id), None);
+            assert!(KittyModule::auctions_ending_at(5).is_empty());
+            assert_balances(1, 1_150, 0);
+            assert_balances(2, 1_000, 0);
+            assert_balances(3, 850, 0);
+
+            // 精算後はロックが外れ、新しい所有者が移転できる
+            assert_ok!(KittyModule::transfer(Origin::signed(3), 2, kitty_id));
+            assert_integrity();
+        });
+    }
+
+    #[test]
+    fn settle_auction_without_bids_cancels_the_auction() {
+        with_externalities(&mut ExtBuilder::default().kitties(vec![(1, 1)]).build(), || {
+            let kitty_id = KittyModule::kitty_of_owner_by_index((1, 0));
+            assert_ok!(KittyModule::create_auction(Origin::signed(1), kitty_id, 100, 5));
+
+            system::Module::<Test>::set_block_number(5);
+            KittyModule::on_finalize(5);
+            assert_integrity();
+
+            assert_eq!(last_event(), TestEvent::kitties(RawEvent::AuctionCancelled(1, kitty_id)));
+            assert_eq!(KittyModule::owner_of(kitty_id), Some(1));
+            assert_eq!(KittyModule::auction_of(kitty_id), None);
+            assert_balances(1, 1_000, 0);
+        });
+    }
+
+    #[test]
+    fn settle_auction_refunds_a_bidder_who_cannot_receive_the_kitty() {
+        with_externalities(&mut ExtBuilder::default().kitties(vec![(1, 1), (2, 1)]).build(), || {
+            let kitty_id = KittyModule::kitty_of_owner_by_index((1, 0));
+            assert_ok!(KittyModule::create_auction(Origin::signed(1), kitty_id, 100, 5));
+            assert_ok!(KittyModule::bid(Origin::signed(2), kitty_id, 100));
+            assert_balances(2, 900, 100);
+
+            // 入札後に上限が設定され、入札者はもうKittyを受け取れない
+            assert_ok!(KittyModule::set_max_owned_per_account(Origin::ROOT, Some(1)));
+
+            system::Module::<Test>::set_block_number(5);
+            KittyModule::on_finalize(5);
+            assert_integrity();
+
+            assert_eq!(last_event(), TestEvent::kitties(RawEvent::AuctionCancelled(1, kitty_id)));
+            assert_eq!(KittyModule::owner_of(kitty_id), Some(1));
+            assert_eq!(KittyModule::auction_of(kitty_id), None);
+            assert_balances(1, 1_000, 0);
+            assert_balances(2, 1_000, 0);
+        });
+    }
+
+    #[test]
+    fn cancel_auction_only_works_for_the_seller_without_bids() {
+        with_externalities(&mut ExtBuilder::default().kitties(vec![(1, 2)]).build(), || {
+            let kitty_1 = KittyModule::kitty_of_owner_by_index((1, 0));
+            let kitty_2 = KittyModule::kitty_of_owner_by_index((1, 1));
+            assert_ok!(KittyModule::create_auction(Origin::signed(1), kitty_1, 100, 5));
+            assert_ok!(KittyModule::create_auction(Origin::signed(1), kitty_2, 100, 5));
+            assert_ok!(KittyModule::bid(Origin::signed(2), kitty_2, 100));
+
+            assert_noop!(
+                KittyModule::cancel_auction(Origin::signed(2), kitty_1),
+                "You are not the seller of this auction"
+            );
+            assert_noop!(
+                KittyModule::cancel_auction(Origin::signed(1), kitty_2),
+                "You can't cancel an auction that has bids"
+            );
+
+            assert_ok!(KittyModule::cancel_auction(Origin::signed(1), kitty_1));
+            assert_integrity();
+            assert_eq!(last_event(), TestEvent::kitties(RawEvent::AuctionCancelled(1, kitty_1)));
+            assert_eq!(KittyModule::auction_of(kitty_1), None);
+            assert_eq!(KittyModule::auctions_ending_at(5), vec![kitty_2]);
+            assert_noop!(
+                KittyModule::cancel_auction(Origin::signed(1), kitty_1),
+                "This kitty is not in an auction"
+            );
+            assert_balances(2, 900, 100);
+
+            // 取り消したKittyは再び移転できる
+            assert_ok!(KittyModule::transfer(Origin::signed(1), 3, kitty_1));
+            assert_integrity();
+        });
+    }
+
+    #[test]
+    fn current_price_falls_linearly_during_a_dutch_auction() {
+        with_externalities(&mut ExtBuilder::default().kitties(vec![(1, 1)]).build(), || {
+            let kitty_id = KittyModule::kitty_of_owner_by_index((1, 0));
+            system::Module::<Test>::set_block_number(1);
+
+            assert_noop!(
+                KittyModule::create_dutch_auction(Origin::signed(2), kitty_id, 200, 100, 3),
+                "You do not own this kitty"
+            );
+            assert_noop!(
+                KittyModule::create_dutch_auction(Origin::signed(1), kitty_id, 200, 0, 3),
+                "The end price must be greater than zero"
+            );
+            assert_noop!(
+                KittyModule::create_dutch_auction(Origin::signed(1), kitty_id, 100, 200, 3),
+                "The start price must not be lower than the end price"
+            );
+            assert_noop!(
+                KittyModule::create_dutch_auction(Origin::signed(1), kitty_id, 200, 100, 0),
+                "The duration must be at least one block"
+            );
+            assert_eq!(KittyModule::current_price(kitty_id), 0);
+
+            // 3ブロックかけて200から100まで下がる
+            assert_ok!(KittyModule::create_dutch_auction(Origin::signed(1), kitty_id, 200, 100, 3));
+            assert_integrity();
+            assert_eq!(last_event(), TestEvent::kitties(RawEvent::DutchAuctionCreated(1, kitty_id, 200, 100, 3)));
+
+            // 値下げ額は切り捨てられる
+            let prices: Vec<u64> = (1..6).map(|n| {
+                system::Module::<Test>::set_block_number(n);
+                KittyModule::current_price(kitty_id)
+            }).collect();
+            assert_eq!(prices, vec![200, 167, 134, 100, 100]);
+
+            // 固定価格を設定するとダッチオークションは終わる
+            assert_ok!(KittyModule::set_price(Origin::signed(1), kitty_id, 150));
+            assert_eq!(KittyModule::dutch_auction_of(kitty_id), None);
+            assert_eq!(KittyModule::current_price(kitty_id), 150);
+        });
+    }
+
+    #[test]
+    fn buy_kitty_pays_the_current_dutch_auction_price() {
+        with_externalities(&mut ExtBuilder::default().kitties(vec![(1, 1)]).build(), || {
+            let kitty_id = KittyModule::kitty_of_owner_by_index((1, 0));
+            system::Module::<Test>::set_block_number(1);
+            assert_ok!(KittyModule::create_dutch_auction(Origin::signed(1), kitty_id, 200, 100, 3));
+
+            system::Module::<Test>::set_block_number(3);
+            assert_noop!(
+                KittyModule::buy_kitty(Origin::signed(2), kitty_id, 133),
+                "The cat you want to buy is costs more than your max price"
+            );
+
+            assert_ok!(KittyModule::buy_kitty(Origin::signed(2), kitty_id, 134));
+            assert_integrity();
+            assert_eq!(last_event(), TestEvent::kitties(RawEvent::Bought(2, 1, kitty_id, 134)));
+            assert_eq!(KittyModule::owner_of(kitty_id), Some(2));
+            assert_eq!(KittyModule::dutch_auction_of(kitty_id), None);
+            assert_eq!(KittyModule::current_price(kitty_id), 0);
+            assert_eq!(KittyModule::for_sale_count(), 0);
+            assert_balances(1, 1_134, 0);
+            assert_balances(2, 866, 0);
+        });
+    }
+
+    #[test]
+    fn accept_offer_pays_the_owner_and_refunds_the_other_offers() {
+        with_externalities(&mut ExtBuilder::default().kitties(vec![(1, 1)]).build(), || {
+            let kitty_id = KittyModule::kitty_of_owner_by_index((1, 0));
+            system::Module::<Test>::set_block_number(1);
+
+            assert_noop!(
+                KittyModule::make_offer(Origin::signed(1), kitty_id, 100, 10),
+                "You can't make an offer on your own kitty"
+            );
+            assert_noop!(
+                KittyModule::make_offer(Origin::signed(2), kitty_id, 9, 10),
+                "The offer is below the minimum amount"
+            );
+            assert_noop!(
+                KittyModule::make_offer(Origin::signed(2), kitty_id, 100, 1),
+                "The offer must expire in the future"
+            );
+
+            assert_ok!(KittyModule::make_offer(Origin::signed(2), kitty_id, 100, 10));
+            assert_integrity();
+            assert_eq!(last_event(), TestEvent::kitties(RawEvent::OfferMade(2, kitty_id, 100, 10)));
+            assert_noop!(
+                KittyModule::make_offer(Origin::signed(2), kitty_id, 120, 10),
+                "You already have an offer on this kitty"
+            );
+            assert_ok!(KittyModule::make_offer(Origin::signed(3), kitty_id, 80, 10));
+            assert_integrity();
+            assert_balances(2, 900, 100);
+            assert_balances(3, 920, 80);
+
+            assert_noop!(KittyModule::accept_offer(Origin::signed(2), kitty_id, 2), "You do not own this kitty");
+            assert_noop!(
+                KittyModule::accept_offer(Origin::signed(1), kitty_id, 1),
+                "There is no offer from this account"
+            );
+
+            assert_ok!(KittyModule::set_price(Origin::signed(1), kitty_id, 500));
+            assert_ok!(KittyModule::accept_offer(Origin::signed(1), kitty_id, 2));
+            assert_integrity();
+            assert_eq!(last_event(), TestEvent::kitties(RawEvent::OfferAccepted(1, 2, kitty_id, 100)));
+
+            assert_eq!(KittyModule::owner_of(kitty_id), Some(2));
+            assert_eq!(KittyModule::for_sale_count(), 0);
+            assert_balances(1, 1_100, 0);
+            assert_balances(2, 900, 0);
+            // 所有者が変わると残りのオファーは返却される
+            assert_eq!(KittyModule::offer_of(&kitty_id, &3), None);
+            assert!(KittyModule::offer_bidders(kitty_id).is_empty());
+            assert_balances(3, 1_000, 0);
+        });
+    }
+
+    #[test]
+    fn withdrawn_and_expired_offers_are_refunded() {
+        with_externalities(&mut ExtBuilder::default().kitties(vec![(1, 1)]).build(), || {
+            let kitty_id = KittyModule::kitty_of_owner_by_index((1, 0));
+            system::Module::<Test>::set_block_number(1);
+            assert_ok!(KittyModule::make_offer(Origin::signed(2), kitty_id, 100, 5));
+            assert_ok!(KittyModule::make_offer(Origin::signed(3), kitty_id, 50, 10));
+
+            assert_noop!(KittyModule::withdraw_offer(Origin::signed(1), kitty_id), "You have no offer on this kitty");
+            assert_ok!(KittyModule::withdraw_offer(Origin::signed(2), kitty_id));
+            assert_integrity();
+            assert_eq!(last_event(), TestEvent::kitties(RawEvent::OfferWithdrawn(2, kitty_id)));
+            assert_eq!(KittyModule::offer_bidders(kitty_id), vec![3]);
+            assert_balances(2, 1_000, 0);
+
+            // 取り下げた後に出し直したオファーは、元の期限では失効しない
+            assert_ok!(KittyModule::make_offer(Origin::signed(2), kitty_id, 120, 8));
+            system::Module::<Test>::set_block_number(5);
+            KittyModule::on_finalize(5);
+            assert_integrity();
+            assert_eq!(KittyModule::offer_of(&kitty_id, &2).map(|offer| offer.amount), Some(120));
+            assert_balances(2, 880, 120);
+
+            system::Module::<Test>::set_block_number(8);
+            KittyModule::on_finalize(8);
+            assert_integrity();
+            assert_eq!(last_event(), TestEvent::kitties(RawEvent::OfferExpired(2, kitty_id)));
+            assert_eq!(KittyModule::offer_of(&kitty_id, &2), None);
+            assert_balances(2, 1_000, 0);
+
+            system::Module::<Test>::set_block_number(10);
+            KittyModule::on_finalize(10);
+            assert_integrity();
+            assert_eq!(last_event(), TestEvent::kitties(RawEvent::OfferExpired(3, kitty_id)));
+            assert!(KittyModule::offer_bidders(kitty_id).is_empty());
+            assert_balances(3, 1_000, 0);
+            assert_balances(1, 1_000, 0);
+        });
+    }
+
+    #[test]
+    fn make_offer_replaces_the_lowest_offer_when_the_kitty_has_too_many() {
+        let mut ext = ExtBuilder::default()
+            .balances(vec![(1, 1_000), (2, 1_000), (3, 1_000), (4, 1_000)])
+            .kitties(vec![(1, 1)])
+            .build();
+        with_externalities(&mut ext, || {
+            let kitty_id = KittyModule::kitty_of_owner_by_index((1, 0));
+            system::Module::<Test>::set_block_number(1);
+            assert_ok!(KittyModule::make_offer(Origin::signed(2), kitty_id, 100, 10));
+            assert_ok!(KittyModule::make_offer(Origin::signed(3), kitty_id, 50, 10));
+
+            // モックでは1匹あたりのオファーは2件まで
+            assert_noop!(
+                KittyModule::make_offer(Origin::signed(4), kitty_id, 50, 10),
+                "The offer must be higher than the lowest offer on this kitty"
+            );
+
+            assert_ok!(KittyModule::make_offer(Origin::signed(4), kitty_id, 60, 10));
+            assert_integrity();
+            let events = kitty_events();
+            assert_eq!(&events[events.len() - 2..], &[
+                RawEvent::OfferOutbid(3, kitty_id),
+                RawEvent::OfferMade(4, kitty_id, 60, 10),
+            ]);
+            assert_eq!(KittyModule::offer_bidders(kitty_id), vec![2, 4]);
+            assert_eq!(KittyModule::offer_of(&kitty_id, &3), None);
+            assert_balances(3, 1_000, 0);
+            assert_balances(4, 940, 60);
+
+            // 入れ替えられたオファーは期限を迎えても何もしない
+            system::Module::<Test>::set_block_number(10);
+            KittyModule::on_finalize(10);
+            assert_integrity();
+            assert_balances(2, 1_000, 0);
+            assert_balances(3, 1_000, 0);
+            assert_balances(4, 1_000, 0);
+        });
+    }
+
+    #[test]
+    fn buy_kitty_pays_a_royalty_to_the_creator() {
+        with_externalities(&mut ExtBuilder::default().kitties(vec![(1, 1)]).build(), || {
+            let kitty_id = KittyModule::kitty_of_owner_by_index((1, 0));
+
+            assert!(KittyModule::set_royalty_rate(Origin::signed(1), Permill::from_percent(10)).is_err());
+            assert_ok!(KittyModule::set_royalty_rate(Origin::ROOT, Permill::from_percent(10)));
+            assert_eq!(last_event(), TestEvent::kitties(RawEvent::RoyaltyRateSet(Permill::from_percent(10))));
+
+            // 作成者自身が売る場合はロイヤリティは発生しない
+            assert_ok!(KittyModule::set_price(Origin::signed(1), kitty_id, 100));
+            assert_ok!(KittyModule::buy_kitty(Origin::signed(2), kitty_id, 100));
+            assert_integrity();
+            assert_eq!(last_event(), TestEvent::kitties(RawEvent::Bought(2, 1, kitty_id, 100)));
+            assert_balances(1, 1_100, 0);
+            assert_balances(2, 900, 0);
+
+            assert_ok!(KittyModule::set_price(Origin::signed(2), kitty_id, 200));
+            assert_ok!(KittyModule::buy_kitty(Origin::signed(3), kitty_id, 200));
+            assert_integrity();
+            let events = kitty_events();
+            assert_eq!(&events[events.len() - 3..], &[
+                RawEvent::RoyaltyPaid(1, kitty_id, 20),
+                RawEvent::Transferred(2, 3, kitty_id),
+                RawEvent::Bought(3, 2, kitty_id, 200),
+            ]);
+            assert_balances(1, 1_120, 0);
+            assert_balances(2, 1_080, 0);
+            assert_balances(3, 800, 0);
+            assert_eq!(KittyModule::creator_of(kitty_id), Some(1));
+        });
+    }
+
+    #[test]
+    fn accept_offer_pays_the_royalty_from_the_reserved_offer() {
+        with_externalities(&mut ExtBuilder::default().kitties(vec![(1, 1)]).build(), || {
+            let kitty_id = KittyModule::kitty_of_owner_by_index((1, 0));
+            assert_ok!(KittyModule::set_royalty_rate(Origin::ROOT, Permill::from_percent(10)));
+            assert_ok!(KittyModule::transfer(Origin::signed(1), 2, kitty_id));
+            system::Module::<Test>::set_block_number(1);
+
+            assert_ok!(KittyModule::make_offer(Origin::signed(3), kitty_id, 100, 10));
+            assert_balances(3, 900, 100);
+
+            assert_ok!(KittyModule::accept_offer(Origin::signed(2), kitty_id, 3));
+            assert_integrity();
+            assert_eq!(KittyModule::owner_of(kitty_id), Some(3));
+            assert_balances(1, 1_010, 0);
+            assert_balances(2, 1_090, 0);
+            assert_balances(3, 900, 0);
+            assert!(kitty_events().contains(&RawEvent::RoyaltyPaid(1, kitty_id, 10)));
+        });
+    }
+
+    #[test]
+    fn settle_auction_pays_the_royalty_from_the_reserved_bid() {
+        with_externalities(&mut ExtBuilder::default().kitties(vec![(1, 1)]).build(), || {
+            let kitty_id = KittyModule::kitty_of_owner_by_index((1, 0));
+            assert_ok!(KittyModule::set_royalty_rate(Origin::ROOT, Permill::from_percent(10)));
+            assert_ok!(KittyModule::transfer(Origin::signed(1), 2, kitty_id));
+
+            assert_ok!(KittyModule::create_auction(Origin::signed(2), kitty_id, 100, 5));
+            assert_ok!(KittyModule::bid(Origin::signed(3), kitty_id, 150));
+            assert_balances(3, 850, 150);
+
+            system::Module::<Test>::set_block_number(5);
+            KittyModule::on_finalize(5);
+            assert_integrity();
+            assert_eq!(last_event(), TestEvent::kitties(RawEvent::AuctionSettled(2, 3, kitty_id, 150)));
+            assert_eq!(KittyModule::owner_of(kitty_id), Some(3));
+            assert_balances(1, 1_015, 0);
+            assert_balances(2, 1_135, 0);
+            assert_balances(3, 850, 0);
+            assert!(kitty_events().contains(&RawEvent::RoyaltyPaid(1, kitty_id, 15)));
+        });
+    }
+
+    #[test]
+    fn royalty_goes_to_the_seller_when_the_creator_account_cannot_be_created() {
+        // 作成者のアカウントは残高が無く、ロイヤリティは存在預金に満たない
+        let mut ext = ExtBuilder::default()
+            .balances(vec![(2, 1_000), (3, 1_000)])
+            .existential_deposit(50)
+            .kitties(vec![(1, 1)])
+            .build();
+        with_externalities(&mut ext, || {
+            let kitty_id = KittyModule::kitty_of_owner_by_index((1, 0));
+            assert_ok!(KittyModule::set_royalty_rate(Origin::ROOT, Permill::from_percent(10)));
+            assert_ok!(KittyModule::transfer(Origin::signed(1), 2, kitty_id));
+
+            assert_ok!(KittyModule::set_price(Origin::signed(2), kitty_id, 100));
+            assert_ok!(KittyModule::buy_kitty(Origin::signed(3), kitty_id, 100));
+            assert_integrity();
+
+            assert_eq!(KittyModule::owner_of(kitty_id), Some(3));
+            assert_balances(1, 0, 0);
+            assert_balances(2, 1_100, 0);
+            assert_balances(3, 900, 0);
+            assert!(!kitty_events().iter().any(|event| match event {
+                RawEvent::RoyaltyPaid(..) => true,
+                _ => false,
+            }));
+        });
+    }
+
+    // ランダムな呼び出し列を実行し、各ステップの後に所有関係の不変条件を確かめる
+    // 失敗した呼び出し列はquickcheckが最小の再現手順まで縮める
+    mod fuzz {
+        use super::*;
+        use std::collections::HashMap;
+        use quickcheck::{Arbitrary, Gen, QuickCheck, TestResult};
+
+        const ACCOUNTS: u64 = 3;
+        const MAX_PRICE: u64 = 600;
+
+        // Kittyは作成順のインデックスで指定し、存在するKittyの数で割った余りを使う
+        #[derive(Clone, Debug)]
+        enum Call {
+            CreateKitty(u64),
+            Transfer(u64, u64, usize),
+            SetPrice(u64, usize, u64),
+            BuyKitty(u64, usize, u64),
+            BreedKitty(u64, usize, usize),
+        }
+
+        fn account<G: Gen>(g: &mut G) -> u64 {
+            u64::arbitrary(g) % ACCOUNTS + 1
+        }
+
+        fn price<G: Gen>(g: &mut G) -> u64 {
+            u64::arbitrary(g) % MAX_PRICE
+        }
+
+        impl Arbitrary for Call {
+            fn arbitrary<G: Gen>(g: &mut G) -> Self {
+                match u8::arbitrary(g) % 5 {
+                    0 => Call::CreateKitty(account(g)),
+                    1 => Call::Transfer(account(g), account(g), usize::arbitrary(g)),
+                    2 => Call::SetPrice(account(g), usize::arbitrary(g), price(g)),
+                    3 => Call::BuyKitty(account(g), usize::arbitrary(g), price(g)),
+                    _ => Call::BreedKitty(account(g), usize::arbitrary(g), usize::arbitrary(g)),
+                }
+            }
+
+            // アカウントはそのままに、Kittyのインデックスと価格を小さくしていく
+            fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
+                match self.clone() {
+                    Call::CreateKitty(_) => quickcheck::empty_shrinker(),
+                    Call::Transfer(from, to, kitty) => Box::new(
+                        kitty.shrink().map(move |kitty| Call::Transfer(from, to, kitty))
+                    ),
+                    Call::SetPrice(owner, kitty, price) => Box::new(
+                        (kitty, price).shrink().map(move |(kitty, price)| Call::SetPrice(owner, kitty, price))
+                    ),
+                    Call::BuyKitty(buyer, kitty, max_price) => Box::new(
+                        (kitty, max_price).shrink().map(move |(kitty, max_price)| Call::BuyKitty(buyer, kitty, max_price))
+                    ),
+                    Call::BreedKitty(owner, kitty_1, kitty_2) => Box::new(
+                        (kitty_1, kitty_2).shrink().map(move |(kitty_1, kitty_2)| Call::BreedKitty(owner, kitty_1, kitty_2))
+                    ),
+                }
+            }
+        }
+
+        fn nth_kitty(index: usize) -> Option<H256> {
+            let count = KittyModule::all_kitties_count();
+            if count == 0 {
+                None
+            } else {
+                Some(KittyModule::kitty_by_index(index as u64 % count))
+            }
+        }
+
+        // 呼び出しを実行し、成功した場合はモデル(KittyId => 所有者)を更新する
+        fn execute(step: usize, call: &Call, owners: &mut HashMap<H256, u64>) {
+            match *call {
+                Call::CreateKitty(owner) => {
+                    // ステップごとに異なるsecretでコミットし、REVEAL_DELAY後にrevealする
+                    let secret = H256::from_low_u64_be(step as u64);
+                    let commitment = BlakeTwo256::hash_of(&secret);
+                    if KittyModule::commit_create(Origin::signed(owner), commitment).is_ok() {
+                        wait_for_reveal();
+                        if KittyModule::reveal_create(Origin::signed(owner), secret).is_ok() {
+                            let count = KittyModule::owned_kitty_count(owner);
+                            owners.insert(KittyModule::kitty_of_owner_by_index((owner, count - 1)), owner);
+                        }
+                    }
+                },
+                Call::Transfer(from, to, kitty) => {
+                    if let Some(kitty_id) = nth_kitty(kitty) {
+                        if KittyModule::transfer(Origin::signed(from), to, kitty_id).is_ok() {
+                            owners.insert(kitty_id, to);
+                        }
+                    }
+                },
+                Call::SetPrice(owner, kitty, price) => {
+                    if let Some(kitty_id) = nth_kitty(kitty) {
+                        let _ = KittyModule::set_price(Origin::signed(owner), kitty_id, price);
+                    }
+                },
+                Call::BuyKitty(buyer, kitty, max_price) => {
+                    if let Some(kitty_id) = nth_kitty(kitty) {
+                        if KittyModule::buy_kitty(Origin::signed(buyer), kitty_id, max_price).is_ok() {
+                            owners.insert(kitty_id, buyer);
+                        }
+                    }
+                },
+                Call::BreedKitty(owner, kitty_1, kitty_2) => {
+                    if let (Some(kitty_id_1), Some(kitty_id_2)) = (nth_kitty(kitty_1), nth_kitty(kitty_2)) {
+                        // ステップごとに異なるsecretでコミットし、REVEAL_DELAY後にrevealする
+                        let secret = H256::from_low_u64_be(step as u64);
+                        let commitment = BlakeTwo256::hash_of(&secret);
+                        if KittyModule::commit_breed(Origin::signed(owner), kitty_id_1, kitty_id_2, commitment).is_ok() {
+                            wait_for_reveal();
+                            if KittyModule::reveal_breed(Origin::signed(owner), secret).is_ok() {
+                                let child = KittyModule::kitty_by_index(KittyModule::all_kitties_count() - 1);
+                                owners.insert(child, owner);
+                            }
+                        }
+                    }
+                },
+            }
+        }
+
+        // 不変条件が破れていればその内容を返す
+        fn check_invariants(owners: &HashMap<H256, u64>) -> Option<String> {
+            if let Err(errors) = KittyModule::check_integrity() {
+                return Some(format!("check_integrity failed: {:?}", errors));
+            }
+
+            let all_kitties_count = KittyModule::all_kitties_count();
+            let owned_total: u64 = (1..=ACCOUNTS).map(KittyModule::owned_kitty_count).sum();
+            if owned_total != all_kitties_count {
+                return Some(format!("accounts own {} kitties but {} exist", owned_total, all_kitties_count));
+            }
+            if owners.len() as u64 != all_kitties_count {
+                return Some(format!("{} kitties were minted but {} exist", owners.len(), all_kitties_count));
+            }
+            for (kitty_id, owner) in owners {
+                if KittyModule::owner_of(kitty_id) != Some(*owner) {
+                    return Some(format!(
+                        "kitty {:?} should be owned by {} but is owned by {:?}",
+                        kitty_id, owner, KittyModule::owner_of(kitty_id)
+                    ));
+                }
+            }
+
+            let listed = (0..all_kitties_count)
+                .filter(|index| !KittyModule::kitty(KittyModule::kitty_by_index(*index)).price.is_zero())
+                .count() as u64;
+            if listed != KittyModule::for_sale_count() {
+                return Some(format!("{} kitties have a price but for_sale_count is {}", listed, KittyModule::for_sale_count()));
+            }
+
+            None
+        }
+
+        fn calls_keep_invariants(calls: Vec<Call>) -> TestResult {
+            with_externalities(&mut new_test_ext(), || {
+                let mut owners = HashMap::new();
+                for (step, call) in calls.iter().enumerate() {
+                    execute(step, call, &mut owners);
+                    if let Some(error) = check_invariants(&owners) {
+                        return TestResult::error(format!("after step {} ({:?}): {}", step, call, error));
+                    }
+                }
+                TestResult::passed()
+            })
+        }
+
+        #[test]
+        fn random_calls_keep_ownership_consistent() {
+            QuickCheck::new()
+                .tests(200)
+                .quickcheck(calls_keep_invariants as fn(Vec<Call>) -> TestResult);
+        }
+    }
+}
@@ -1,13 +1,13 @@
-use crate::service;
+use crate::{service, rpc, integrity};
 use futures::{future, Future, sync::oneshot};
 use std::cell::RefCell;
 use tokio::runtime::Runtime;
 pub use substrate_cli::{VersionInfo, IntoExit, error};
-use substrate_cli::{informant, parse_and_execute, NoCustom};
+use substrate_cli::{informant, parse_and_execute};
 use substrate_service::{ServiceFactory, Roles as ServiceRoles};
 use crate::chain_spec;
 use std::ops::Deref;
-use log::info;
+use log::{info, warn};
 
 /// Parse command line arguments into service configuration.
 pub fn run<I, T, E>(args: I, exit: E, version: VersionInfo) -> error::Result<()> where
@@ -15,9 +15,9 @@ pub fn run<I, T, E>(args: I, exit: E, version: VersionInfo) -> error::Result<()>
 	T: Into<std::ffi::OsString> + Clone,
 	E: IntoExit,
 {
-	parse_and_execute::<service::Factory, NoCustom, NoCustom, _, _, _, _, _>(
+	let custom = parse_and_execute::<service::Factory, integrity::CustomCommand, rpc::RpcParams, _, _, _, _, _>(
 		load_spec, &version, "substrate-node", args, exit,
-	 	|exit, _custom_args, config| {
+	 	|exit, rpc_params, config| {
 			info!("{}", version.name);
 			info!("  version {}", config.full_version());
 			info!("  by {}, 2019", version.author);
@@ -32,14 +32,33 @@ pub fn run<I, T, E>(args: I, exit: E, version: VersionInfo) -> error::Result<()>
 				 	service::Factory::new_light(config, executor).map_err(|e| format!("{:?}", e))?,
 					exit
 				),
-				_ => run_until_exit(
-					runtime,
-					service::Factory::new_full(config, executor).map_err(|e| format!("{:?}", e))?,
-					exit
-				),
+				_ => {
+					let service = service::Factory::new_full(config, executor).map_err(|e| format!("{:?}", e))?;
+					// The kitties RPC server lives as long as the node is running. If the port is taken,
+					// e.g. by a second local node, the node keeps running without it.
+					let _kitties_rpc = match rpc_params.address() {
+						Some(address) => match rpc::start_http(&address, service.client()) {
+							Ok(server) => {
+								info!("Kitties RPC listening on {}", address);
+								Some(server)
+							},
+							Err(e) => {
+								warn!("Unable to start kitties RPC server on {}: {:?}", address, e);
+								None
+							},
+						},
+						None => None,
+					};
+					run_until_exit(runtime, service, exit)
+				},
 			}.map_err(|e| format!("{:?}", e))
 		}
-	).map_err(Into::into).map(|_| ())
+	)?;
+
+	match custom {
+		Some(integrity::CustomCommand::CheckKitties(cmd)) => integrity::check_kitties(cmd, load_spec, &version),
+		None => Ok(()),
+	}
 }
 
 fn load_spec(id: &str) -> Result<Option<chain_spec::ChainSpec>, String> {
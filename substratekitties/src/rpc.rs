@@ -0,0 +1,189 @@
+//! Kitties JSON-RPC API.
+//!
+//! The service factory only serves the stock RPC, so the `kitties_*` namespace is served by its
+//! own HTTP server started next to the full node. All calls go through the `KittiesApi` runtime
+//! API at the best block, so they keep working when the module's storage layout changes.
+
+use std::{io, net::SocketAddr, sync::Arc};
+use jsonrpc_core::{Error, ErrorCode, IoHandler, Result};
+use jsonrpc_derive::rpc;
+use jsonrpc_http_server::{Server, ServerBuilder};
+use serde::Serialize;
+use structopt::StructOpt;
+use substrate_cli::impl_augment_clap;
+use substrate_client::blockchain::HeaderBackend;
+use sr_primitives::traits::ProvideRuntimeApi;
+use substratekitties_runtime::{
+	AccountId, BlockNumber, Hash, Kitty, genetics::Gender, kitties_api::KittiesApi, opaque::{Block, BlockId},
+};
+
+/// Flags of the `run` command for the kitties RPC server.
+#[derive(Debug, StructOpt, Clone)]
+pub struct RpcParams {
+	/// Specify the kitties JSON-RPC HTTP server port. It only listens on localhost.
+	#[structopt(long = "kitties-rpc-port", value_name = "PORT", default_value = "9934")]
+	pub kitties_rpc_port: u16,
+
+	/// Don't start the kitties JSON-RPC HTTP server.
+	#[structopt(long = "no-kitties-rpc")]
+	pub no_kitties_rpc: bool,
+}
+
+impl_augment_clap!(RpcParams);
+
+impl RpcParams {
+	/// The address the kitties RPC server should listen on, or `None` if it is disabled.
+	pub fn address(&self) -> Option<SocketAddr> {
+		if self.no_kitties_rpc {
+			None
+		} else {
+			Some(([127, 0, 0, 1], self.kitties_rpc_port).into())
+		}
+	}
+}
+
+/// A kitty as returned over RPC, with its DNA in hex and prices as decimal strings.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcKitty {
+	pub id: Hash,
+	pub dna: String,
+	pub price: String,
+	pub gen: u64,
+	pub ready_at: BlockNumber,
+	pub sire_fee: Option<String>,
+	pub gender: Gender,
+}
+
+impl From<Kitty> for RpcKitty {
+	fn from(kitty: Kitty) -> Self {
+		RpcKitty {
+			id: kitty.id,
+			dna: format!("0x{}", kitty.dna.as_ref().iter().map(|b| format!("{:02x}", b)).collect::<String>()),
+			price: kitty.price.to_string(),
+			gen: kitty.gen,
+			ready_at: kitty.ready_at,
+			sire_fee: kitty.sire_fee.map(|fee| fee.to_string()),
+			gender: kitty.gender,
+		}
+	}
+}
+
+/// The direct family of a kitty.
+#[derive(Serialize)]
+pub struct Pedigree {
+	pub kitty: Hash,
+	pub parents: Option<(Hash, Hash)>,
+	pub children: Vec<Hash>,
+}
+
+/// Kitties RPC methods.
+#[rpc]
+pub trait KittiesRpc {
+	/// Returns the kitty with the given id, if it exists.
+	#[rpc(name = "kitties_getKitty")]
+	fn get_kitty(&self, kitty_id: Hash) -> Result<Option<RpcKitty>>;
+
+	/// Returns the kitty with the given name, if there is one.
+	#[rpc(name = "kitties_getKittyByName")]
+	fn get_kitty_by_name(&self, name: String) -> Result<Option<RpcKitty>>;
+
+	/// Returns up to `limit` kitties owned by `owner`, starting at the `start`th one.
+	#[rpc(name = "kitties_listByOwner")]
+	fn list_by_owner(&self, owner: AccountId, start: u64, limit: u64) -> Result<Vec<RpcKitty>>;
+
+	/// Returns up to `limit` kitties that are for sale, starting at the `start`th one.
+	#[rpc(name = "kitties_listForSale")]
+	fn list_for_sale(&self, start: u64, limit: u64) -> Result<Vec<RpcKitty>>;
+
+	/// Returns the parents and children of a kitty.
+	#[rpc(name = "kitties_pedigree")]
+	fn pedigree(&self, kitty_id: Hash) -> Result<Pedigree>;
+}
+
+/// Implementation of `KittiesRpc` on top of a client.
+pub struct Kitties<C> {
+	client: Arc<C>,
+}
+
+impl<C> Kitties<C> {
+	/// Create a new `Kitties` handler backed by `client`.
+	pub fn new(client: Arc<C>) -> Self {
+		Kitties { client }
+	}
+}
+
+impl<C> Kitties<C> where C: HeaderBackend<Block> {
+	fn best_block(&self) -> Result<BlockId> {
+		self.client.info()
+			.map(|info| BlockId::hash(info.best_hash))
+			.map_err(client_error)
+	}
+}
+
+impl<C> KittiesRpc for Kitties<C> where
+	C: ProvideRuntimeApi + HeaderBackend<Block> + Send + Sync + 'static,
+	C::Api: KittiesApi<Block>,
+{
+	fn get_kitty(&self, kitty_id: Hash) -> Result<Option<RpcKitty>> {
+		let at = self.best_block()?;
+		let kitty = self.client.runtime_api().kitty(&at, kitty_id).map_err(client_error)?;
+		Ok(kitty.map(Into::into))
+	}
+
+	fn get_kitty_by_name(&self, name: String) -> Result<Option<RpcKitty>> {
+		let at = self.best_block()?;
+		let kitty = self.client.runtime_api().kitty_by_name(&at, name.into_bytes()).map_err(client_error)?;
+		Ok(kitty.map(Into::into))
+	}
+
+	fn list_by_owner(&self, owner: AccountId, start: u64, limit: u64) -> Result<Vec<RpcKitty>> {
+		let at = self.best_block()?;
+		let kitties = self.client.runtime_api().kitties_of_owner(&at, owner, start, limit).map_err(client_error)?;
+		Ok(kitties.into_iter().map(Into::into).collect())
+	}
+
+	fn list_for_sale(&self, start: u64, limit: u64) -> Result<Vec<RpcKitty>> {
+		let at = self.best_block()?;
+		let api = self.client.runtime_api();
+		let kitties = api.kitties_for_sale(&at, start, limit).map_err(client_error)?;
+
+		// Report the price a buyer would pay right now, which differs from the
+		// stored price for kitties in a declining-price auction.
+		kitties.into_iter().map(|kitty| {
+			let price = api.current_price(&at, kitty.id).map_err(client_error)?;
+			let mut kitty = RpcKitty::from(kitty);
+			kitty.price = price.to_string();
+			Ok(kitty)
+		}).collect()
+	}
+
+	fn pedigree(&self, kitty_id: Hash) -> Result<Pedigree> {
+		let at = self.best_block()?;
+		let api = self.client.runtime_api();
+		Ok(Pedigree {
+			kitty: kitty_id,
+			parents: api.parents(&at, kitty_id).map_err(client_error)?,
+			children: api.children(&at, kitty_id).map_err(client_error)?,
+		})
+	}
+}
+
+fn client_error<E: std::fmt::Debug>(err: E) -> Error {
+	Error {
+		code: ErrorCode::ServerError(1),
+		message: "Unable to query kitties.".into(),
+		data: Some(format!("{:?}", err).into()),
+	}
+}
+
+/// Start the kitties RPC HTTP server on `addr`. The server stops when the returned handle is dropped.
+pub fn start_http<C>(addr: &SocketAddr, client: Arc<C>) -> io::Result<Server> where
+	C: ProvideRuntimeApi + HeaderBackend<Block> + Send + Sync + 'static,
+	C::Api: KittiesApi<Block>,
+{
+	let mut io = IoHandler::new();
+	io.extend_with(Kitties::new(client).to_delegate());
+
+	ServerBuilder::new(io).start_http(addr)
+}